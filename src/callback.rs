@@ -0,0 +1,105 @@
+use ::proc_macro2::{Ident, TokenStream};
+
+use ::quote::{format_ident, quote};
+
+use ::syn::{
+	ParenthesizedGenericArguments, PathArguments, ReturnType, Type, TypeParamBound,
+	punctuated::Punctuated, token::Plus,
+};
+
+use crate::throws;
+
+/// The `(A, B) -> R` of an `impl Fn(A, B) -> R` (or `FnMut`/`FnOnce`, or `&dyn Fn...`) argument
+pub fn fn_signature(ty: &Type) -> Option<&ParenthesizedGenericArguments> {
+	let bounds: &Punctuated<TypeParamBound, Plus> = match ty {
+		Type::ImplTrait(impl_trait) => &impl_trait.bounds,
+		Type::TraitObject(trait_object) => &trait_object.bounds,
+		Type::Reference(reference) => match &*reference.elem {
+			Type::TraitObject(trait_object) => &trait_object.bounds,
+			_ => return None,
+		},
+		_ => return None,
+	};
+
+	return bounds.iter().find_map(|bound: &TypeParamBound| {
+		let TypeParamBound::Trait(trait_bound) = bound else {
+			return None;
+		};
+		let segment = trait_bound.path.segments.last()?;
+		if !["Fn", "FnMut", "FnOnce"].contains(&segment.ident.to_string().as_str()) {
+			return None;
+		};
+		let PathArguments::Parenthesized(signature) = &segment.arguments else {
+			return None;
+		};
+
+		return Some(signature);
+	});
+}
+
+/// Rejects callbacks in `ty` that return a plain value: a Lua error raised by the
+/// function has to reach the caller, so every callback returns `mlua::Result<R>`
+pub fn validate(ty: &Type) -> darling::Result<()> {
+	if let Type::Tuple(tuple) = ty {
+		let mut errors: darling::error::Accumulator = darling::Error::accumulator();
+		for elem in &tuple.elems {
+			errors.handle(validate(elem));
+		}
+		return errors.finish();
+	};
+
+	let Some(signature) = fn_signature(ty) else {
+		return Ok(());
+	};
+	if throws::result_types(&signature.output).is_none() {
+		let message: &str = "Lua callbacks must return `mlua::Result<_>`, so errors raised by the function reach the caller";
+		return Err(match &signature.output {
+			ReturnType::Default => darling::Error::custom(message).with_span(signature),
+			ReturnType::Type(_, output) => darling::Error::custom(message).with_span(output),
+		});
+	};
+
+	return Ok(());
+}
+
+/// The Rust closure handed to the fn in place of the Lua function `name`.
+///
+/// Reference arguments are cloned into Lua, and the callback returns the Lua error
+/// (callbacks are checked by `validate` to return `mlua::Result<R>`).
+pub fn bind_callback(
+	name: &Ident,
+	ty: &Type,
+	signature: &ParenthesizedGenericArguments,
+) -> TokenStream {
+	let arg_names: Vec<Ident> = (0..signature.inputs.len())
+		.map(|index: usize| format_ident!("{}_{}", name, index))
+		.collect();
+	let arg_tys: Vec<&Type> = signature.inputs.iter().collect();
+	let lua_args: Vec<TokenStream> = arg_names
+		.iter()
+		.zip(&arg_tys)
+		.map(|(arg_name, arg_ty)| match arg_ty {
+			Type::Reference(_) => quote! { ::std::clone::Clone::clone(#arg_name) },
+			_ => quote! { #arg_name },
+		})
+		.collect();
+
+	let output: Type = match &signature.output {
+		ReturnType::Default => ::syn::parse_quote! { () },
+		ReturnType::Type(_, output) => (**output).clone(),
+	};
+	let closure: TokenStream = quote! {
+		{
+			let function: mlua::Function = #name;
+			move |#(#arg_names: #arg_tys),*| -> #output {
+				return function.call((#(#lua_args,)*));
+			}
+		}
+	};
+
+	return match ty {
+		Type::Reference(reference) if reference.mutability.is_some() => quote! { &mut #closure },
+		Type::Reference(_) => quote! { &#closure },
+		_ => closure,
+	};
+}
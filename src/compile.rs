@@ -1,42 +1,107 @@
 use ::syn::{
-	Result,
-	/*parse::{
-		self, Parse, ParseStream, Parser
-	},*/
-	//parse_macro_input,
-	//braced::{ },
-	//parenthesized::{ },
-	//punctuated::{ Punctuated, },
-	//Path,
-	//Token,
-	/*Type,*/
-	TypePath,
+	Generics, Ident, LitStr, Result, Token, Type, TypePath, WherePredicate,
+	parse::{ParseStream, Parser},
 };
 
 use ::proc_macro::TokenStream;
 
-use ::darling::FromMeta;
+use ::darling::{FromMeta, ast::NestedMeta, util::PathList};
+
+use crate::meta::MetaArgs;
+use crate::ops::OpsArgs;
 
 #[derive(Debug, FromMeta)]
-#[darling(derive_syn_parse)]
 pub struct CompileArgs {
 	#[darling(default)]
 	pub type_path: Option<TypePath>, // ! This isn't optional ;)
+	/// Type parameters of a generic `impl`, e.g. `generics = <T>`
+	#[darling(skip)]
+	pub generics: Generics,
+	/// Extra where-predicates for a generic `impl`, e.g. `bounds = "T: Default"`
+	#[darling(default)]
+	pub bounds: Vec<WherePredicate>,
 	#[darling(default)]
 	pub fields: Option<bool>,
 	#[darling(default)]
 	pub methods: Option<bool>,
 	#[darling(default)]
 	pub variants: Option<bool>,
+	/// Metamethods derived from std traits, e.g. `meta(display, eq, ord)`
+	#[darling(default)]
+	pub meta: MetaArgs,
+	/// Operator metamethods from `std::ops`, e.g. `ops(add, mul = f32, neg)`
+	#[darling(default)]
+	pub ops: OpsArgs,
+	/// Tagged `#[implementation]` blocks to include, e.g. `tags(combat, inventory)`
+	#[darling(default)]
+	pub tags: PathList,
+	/// `#[statics]` modules whose items become static fields, e.g. `statics(config)`
+	#[darling(default)]
+	pub statics: PathList,
 }
 
+/// `type_path` and `generics` take bare types (`Grid<f32>`, `<T>`) which are not
+/// valid meta values, so they are parsed by hand. Everything else goes through darling.
 pub fn parse_compile_args(input: TokenStream) -> Result<CompileArgs> {
-	match syn::parse(input) {
-		Ok(value) => {
-			return Ok(value);
-		}
-		Err(e) => {
-			return Err(e);
+	let parser = |input: ParseStream| -> Result<CompileArgs> {
+		let mut type_path: Option<TypePath> = None;
+		let mut generics: Option<Generics> = None;
+		let mut items: Vec<NestedMeta> = Vec::new();
+
+		while !input.is_empty() {
+			let fork: ParseStream = &input.fork();
+			let key: Option<Ident> = fork.parse::<Ident>().ok().filter(|_| fork.peek(Token![=]));
+
+			match key {
+				Some(key) if key == "type_path" => {
+					input.parse::<Ident>()?;
+					input.parse::<Token![=]>()?;
+					if type_path.is_some() {
+						return Err(syn::Error::new(key.span(), "duplicate `type_path`"));
+					};
+
+					type_path = Some(if input.peek(LitStr) {
+						input.parse::<LitStr>()?.parse()?
+					} else {
+						match input.parse::<Type>()? {
+							Type::Path(path) => path,
+							other => {
+								return Err(syn::Error::new_spanned(other, "expected a type path"));
+							}
+						}
+					});
+				}
+				Some(key) if key == "generics" => {
+					input.parse::<Ident>()?;
+					input.parse::<Token![=]>()?;
+					if generics.is_some() {
+						return Err(syn::Error::new(key.span(), "duplicate `generics`"));
+					};
+
+					generics = Some(if input.peek(LitStr) {
+						input.parse::<LitStr>()?.parse()?
+					} else {
+						input.parse()?
+					});
+				}
+				_ => items.push(input.parse()?),
+			};
+
+			if !input.is_empty() {
+				input.parse::<Token![,]>()?;
+			};
 		}
+
+		let mut args: CompileArgs = CompileArgs::from_list(&items)?;
+		args.meta.validate()?;
+		if let Some(tag) = args.tags.iter().find(|tag| tag.get_ident().is_none()) {
+			return Err(syn::Error::new_spanned(tag, "expected a tag name"));
+		};
+		args.type_path = type_path;
+		args.generics = generics.unwrap_or_default();
+
+		return Ok(args);
 	};
+
+	return parser.parse(input);
 }
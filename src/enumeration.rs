@@ -0,0 +1,606 @@
+use ::syn::{Fields, Ident, ItemEnum, Type, Variant, WherePredicate};
+
+use ::proc_macro2::{Literal, TokenStream};
+
+use ::quote::{format_ident, quote};
+
+use ::darling::{FromMeta, ast::NestedMeta};
+
+use crate::generics;
+use crate::implementation;
+
+/// Options given through `#[enumeration(...)]`
+#[derive(Debug, Default, FromMeta)]
+pub struct EnumerationArgs {
+	/// Unit variants are zero-argument constructors, `MyEnum.Idle()`, instead of values
+	#[darling(default)]
+	pub unit_functions: bool,
+	/// Converts to and from a plain Lua value instead of userdata
+	#[darling(default)]
+	pub repr: Option<Repr>,
+}
+
+/// The plain Lua value an `#[enumeration(repr = "...")]` converts to
+#[derive(Debug, Clone, Copy, PartialEq, FromMeta)]
+pub enum Repr {
+	/// The variant name, `"Ground"`
+	#[darling(rename = "string")]
+	String,
+	/// The discriminant, `Layer::Air as i64`
+	#[darling(rename = "integer")]
+	Integer,
+	/// A table tagged with the variant name, `{ tag = "Move", dx = 1, dy = 2 }`
+	#[darling(rename = "table")]
+	Table,
+}
+
+pub fn parse_enumeration_args(attr: ::proc_macro::TokenStream) -> darling::Result<EnumerationArgs> {
+	let items: Vec<NestedMeta> = NestedMeta::parse_meta_list(attr.into())?;
+
+	return EnumerationArgs::from_list(&items);
+}
+
+/// A static field holding the unit variant `variant_name`, created once per Lua state
+/// and cached in the registry, so `MyEnum.Idle` is always the same value: it works as
+/// a table key and with `rawequal`.
+///
+/// The cached value is shared by every script, so it stays borrowed for as long as it
+/// lives: methods taking `&mut self` or `self` fail on it instead of changing (or
+/// taking) `MyEnum.Idle` for everyone.
+pub fn unit_variant_field(variant_name: &Ident) -> TokenStream {
+	let variant_name_str: String = variant_name.to_string();
+
+	return quote! {
+		fields.add_field_function_get(#variant_name_str, |lua, _| {
+			let key: String = format!("__magic_unit_{}::{}", ::std::any::type_name::<Self>(), #variant_name_str);
+			if let Some(value) = lua.named_registry_value::<Option<mlua::Value>>(&key)? {
+				return Ok(value);
+			};
+
+			let value: mlua::Value = mlua::IntoLua::into_lua(Self::#variant_name, lua)?;
+			if let Some(user_data) = value.as_userdata() {
+				let guard: mlua::UserDataRef<Self> = user_data.borrow::<Self>()?;
+				user_data.set_named_user_value("__magic_shared", lua.create_any_userdata(guard)?)?;
+			};
+			lua.set_named_registry_value(&key, &value)?;
+			return Ok(value);
+		});
+	};
+}
+
+/// `__eq` telling two values holding the same unit variant apart from the rest, for
+/// values that are not the cached one (returned from Rust, say). Variants with a
+/// payload only equal themselves (`compile!(meta(eq))` replaces this with `PartialEq`).
+fn unit_eq_registration(ast: &ItemEnum) -> Option<TokenStream> {
+	let unit_patterns: Vec<TokenStream> = ast
+		.variants
+		.iter()
+		.filter(|variant: &&Variant| matches!(variant.fields, Fields::Unit))
+		.map(|variant: &Variant| {
+			let variant_name: &Ident = &variant.ident;
+			return quote! { Self::#variant_name };
+		})
+		.collect();
+	if unit_patterns.is_empty() {
+		return None;
+	};
+
+	return Some(quote! {
+		methods.add_meta_function(mlua::MetaMethod::Eq, |_, (a, b): (mlua::AnyUserData, mlua::AnyUserData)| {
+			let (Ok(a), Ok(b)) = (a.borrow::<Self>(), b.borrow::<Self>()) else {
+				return Ok(false);
+			};
+
+			return Ok(matches!(&*a, #(#unit_patterns)|*)
+				&& ::std::mem::discriminant(&*a) == ::std::mem::discriminant(&*b));
+		});
+	});
+}
+
+/// `OnFire` -> `on_fire`, for the `is_<variant>` predicates
+pub fn snake_case(name: &str) -> String {
+	let mut snake: String = String::new();
+	for (index, c) in name.chars().enumerate() {
+		if c.is_uppercase() {
+			if index > 0 {
+				snake.push('_');
+			};
+			snake.extend(c.to_lowercase());
+		} else {
+			snake.push(c);
+		};
+	}
+
+	return snake;
+}
+
+/// A pattern matching `variant` whatever its payload, `Self::Attacking { .. }`
+pub fn any_pattern(variant: &Variant) -> TokenStream {
+	let variant_name: &Ident = &variant.ident;
+
+	return quote! { Self::#variant_name { .. } };
+}
+
+/// `match this { Self::Idle { .. } => "Idle", ... }`, the name of the variant `this` holds
+pub fn kind_expr(ast: &ItemEnum) -> TokenStream {
+	let patterns: Vec<TokenStream> = ast.variants.iter().map(any_pattern).collect();
+	let names: Vec<String> = ast
+		.variants
+		.iter()
+		.map(|variant: &Variant| variant.ident.to_string())
+		.collect();
+
+	return quote! {
+		match this {
+			#(#patterns => #names,)*
+		}
+	};
+}
+
+/// Getters for the `kind` field and for every named payload field, which
+/// fail for variants without that field
+pub fn field_registrations(ast: &ItemEnum) -> darling::Result<Vec<TokenStream>> {
+	let enum_name: String = ast.ident.to_string();
+	let kind: TokenStream = kind_expr(ast);
+
+	let mut registrations: Vec<TokenStream> = vec![quote! {
+		fields.add_field_method_get("kind", |_, this| {
+			return Ok(#kind);
+		});
+	}];
+
+	// Each payload name once, with the variants that carry it
+	let mut payload_fields: Vec<(Ident, Vec<(&Ident, &Type)>)> = Vec::new();
+	for variant in &ast.variants {
+		let Fields::Named(fields) = &variant.fields else {
+			continue;
+		};
+		for field in &fields.named {
+			let field_name: &Ident = field.ident.as_ref().expect("named fields have names");
+			if field_name == "kind" {
+				return Err(darling::Error::custom(
+					"a payload field named `kind` would hide the variant's `kind`",
+				)
+				.with_span(field_name));
+			};
+
+			match payload_fields
+				.iter_mut()
+				.find(|(name, _)| name == field_name)
+			{
+				Some((_, variants)) => variants.push((&variant.ident, &field.ty)),
+				None => {
+					payload_fields.push((field_name.clone(), vec![(&variant.ident, &field.ty)]))
+				}
+			};
+		}
+	}
+
+	for (field_name, variants) in &payload_fields {
+		let field_name_str: String = field_name.to_string();
+		let variant_names: Vec<&Ident> = variants
+			.iter()
+			.map(|(variant_name, _)| *variant_name)
+			.collect();
+
+		registrations.push(quote! {
+			fields.add_field_method_get(#field_name_str, |lua, this| {
+				return match this {
+					#(Self::#variant_names { #field_name, .. } => mlua::IntoLua::into_lua(#field_name.clone(), lua),)*
+					#[allow(unreachable_patterns)]
+					_ => Err(mlua::Error::runtime(format!(
+						"{}.{} has no field '{}'",
+						#enum_name,
+						#kind,
+						#field_name_str
+					))),
+				};
+			});
+		});
+	}
+
+	return Ok(registrations);
+}
+
+/// `is_<variant>` predicates, and `__index` for the positional payload of tuple variants
+pub fn method_registrations(ast: &ItemEnum) -> Vec<TokenStream> {
+	let enum_name: String = ast.ident.to_string();
+	let kind: TokenStream = kind_expr(ast);
+
+	let mut registrations: Vec<TokenStream> = Vec::new();
+	for variant in &ast.variants {
+		let predicate_name: String = format!("is_{}", snake_case(&variant.ident.to_string()));
+		let pattern: TokenStream = any_pattern(variant);
+
+		registrations.push(quote! {
+			methods.add_method(#predicate_name, |_, this, (): ()| {
+				return Ok(matches!(this, #pattern));
+			});
+		});
+	}
+
+	registrations.push(match_registration(ast));
+	registrations.extend(unit_eq_registration(ast));
+
+	// `status[1]`, 1-based like Lua sequences
+	let mut index_arms: Vec<TokenStream> = Vec::new();
+	for variant in &ast.variants {
+		let Fields::Unnamed(fields) = &variant.fields else {
+			continue;
+		};
+		let variant_name: &Ident = &variant.ident;
+		for index in 0..fields.unnamed.len() {
+			let lua_index: Literal = Literal::i64_unsuffixed(index as i64 + 1);
+			let patterns: Vec<TokenStream> = (0..fields.unnamed.len())
+				.map(|other: usize| {
+					if other == index {
+						quote! { value }
+					} else {
+						quote! { _ }
+					}
+				})
+				.collect();
+			index_arms.push(quote! {
+				(Self::#variant_name(#(#patterns,)*), Some(#lua_index)) => {
+					return mlua::IntoLua::into_lua(value.clone(), lua);
+				}
+			});
+		}
+	}
+	if !index_arms.is_empty() {
+		registrations.push(quote! {
+			methods.add_meta_method(mlua::MetaMethod::Index, |lua, this, key: mlua::Value| {
+				match (this, key.as_integer()) {
+					#(#index_arms)*
+					(_, Some(index)) => {
+						return Err(mlua::Error::runtime(format!(
+							"{}.{} has no value at index {}",
+							#enum_name,
+							#kind,
+							index
+						)));
+					}
+					_ => {}
+				};
+				return Err(mlua::Error::runtime(format!("attempt to get an unknown field '{}'", key.to_string()?)));
+			});
+		});
+	};
+
+	return registrations;
+}
+
+/// Markers claiming the `is_<variant>` predicates, the positional `__index` and the
+/// unit variants' `__eq` for the type, so an `#[implementation]` fn or `compile!(ops(index))`
+/// registering one of them too fails to compile instead of replacing it
+/// (see `implementation::export_marker`)
+pub fn export_markers(ast: &ItemEnum) -> Vec<Ident> {
+	let mut markers: Vec<Ident> = ast
+		.variants
+		.iter()
+		.map(|variant: &Variant| {
+			let predicate_name: String = format!("is_{}", snake_case(&variant.ident.to_string()));
+			return implementation::export_marker(&predicate_name, variant.ident.span());
+		})
+		.collect();
+	if let Some(variant) = ast
+		.variants
+		.iter()
+		.find(|variant: &&Variant| matches!(variant.fields, Fields::Unnamed(_)))
+	{
+		markers.push(implementation::export_marker(
+			"__index",
+			variant.ident.span(),
+		));
+	};
+	if let Some(variant) = ast
+		.variants
+		.iter()
+		.find(|variant: &&Variant| matches!(variant.fields, Fields::Unit))
+	{
+		markers.push(implementation::export_marker("__eq", variant.ident.span()));
+	};
+
+	return markers;
+}
+
+/// `value:match{ Variant = function(payload...) ... end, _ = function() ... end }`.
+///
+/// The arms are checked before any is called: every variant needs an arm unless
+/// there is a `_` default, and arms for variants that do not exist are rejected.
+fn match_registration(ast: &ItemEnum) -> TokenStream {
+	let enum_name: String = ast.ident.to_string();
+	let variant_names: Vec<String> = ast
+		.variants
+		.iter()
+		.map(|variant: &Variant| variant.ident.to_string())
+		.collect();
+
+	let arms: Vec<TokenStream> = ast
+		.variants
+		.iter()
+		.map(|variant: &Variant| {
+			let variant_name: &Ident = &variant.ident;
+			let variant_name_str: String = variant_name.to_string();
+			let bindings: Vec<Ident> = match &variant.fields {
+				Fields::Named(fields) => fields
+					.named
+					.iter()
+					.map(|field| field.ident.clone().expect("named fields have names"))
+					.collect(),
+				Fields::Unnamed(fields) => (0..fields.unnamed.len())
+					.map(|index: usize| format_ident!("field{}", index))
+					.collect(),
+				Fields::Unit => Vec::new(),
+			};
+			let pattern: TokenStream = match &variant.fields {
+				Fields::Named(_) => quote! { Self::#variant_name { #(#bindings,)* } },
+				Fields::Unnamed(_) => quote! { Self::#variant_name(#(#bindings,)*) },
+				Fields::Unit => quote! { Self::#variant_name },
+			};
+
+			return quote! {
+				#pattern => (#variant_name_str, mlua::IntoLuaMulti::into_lua_multi((#(#bindings.clone(),)*), lua)?),
+			};
+		})
+		.collect();
+
+	return quote! {
+		methods.add_method("match", |lua, this, arms: mlua::Table| {
+			const VARIANTS: &[&str] = &[#(#variant_names),*];
+
+			for key in arms.pairs::<mlua::Value, mlua::Value>().map(|pair| pair.map(|(key, _)| key)) {
+				let key: mlua::Value = key?;
+				let known: bool = key
+					.as_string()
+					.and_then(|key| key.to_str().ok())
+					.is_some_and(|key| key == "_" || VARIANTS.contains(&&*key));
+				if !known {
+					return Err(mlua::Error::runtime(format!(
+						"{} has no variant '{}'",
+						#enum_name,
+						key.to_string()?
+					)));
+				};
+			}
+			if !arms.contains_key("_")? {
+				let mut missing: Vec<&str> = Vec::new();
+				for variant in VARIANTS {
+					if !arms.contains_key(*variant)? {
+						missing.push(variant);
+					};
+				}
+				if !missing.is_empty() {
+					return Err(mlua::Error::runtime(format!(
+						"non-exhaustive match on {}, missing: {}",
+						#enum_name,
+						missing.join(", ")
+					)));
+				};
+			};
+
+			let (variant, payload): (&str, mlua::MultiValue) = match this {
+				#(#arms)*
+			};
+			let arm: mlua::Function = match arms.get::<Option<mlua::Function>>(variant)? {
+				Some(arm) => arm,
+				None => arms.get::<mlua::Function>("_")?,
+			};
+
+			return arm.call::<mlua::MultiValue>(payload);
+		});
+	};
+}
+
+/// `IntoLua` and `FromLua` impls converting a fieldless enum to and from its `repr`.
+/// Unknown names or values are rejected with the list of valid ones.
+pub fn repr_impls(ast: &ItemEnum, repr: Repr) -> darling::Result<TokenStream> {
+	if repr == Repr::Table {
+		return table_repr_impls(ast);
+	};
+
+	let mut errors: darling::error::Accumulator = darling::Error::accumulator();
+	for variant in &ast.variants {
+		if !matches!(variant.fields, Fields::Unit) {
+			errors.push(
+				darling::Error::custom(
+					"`repr = \"string\"` and `repr = \"integer\"` need a fieldless enum",
+				)
+				.with_span(&variant.ident),
+			);
+		};
+	}
+	errors.finish()?;
+
+	let name: &Ident = &ast.ident;
+	let name_str: String = name.to_string();
+	let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+	let variant_names: Vec<&Ident> = ast.variants.iter().map(|variant| &variant.ident).collect();
+	let variant_name_strs: Vec<String> =
+		variant_names.iter().map(|name| name.to_string()).collect();
+
+	let (into_lua, from_lua): (TokenStream, TokenStream) = match repr {
+		Repr::String => {
+			let expected: String = variant_name_strs.join(", ");
+			(
+				quote! {
+					let name: &str = match self {
+						#(Self::#variant_names => #variant_name_strs,)*
+					};
+					return mlua::IntoLua::into_lua(name, lua);
+				},
+				quote! {
+					let from: &'static str = value.type_name();
+					let name: mlua::String = mlua::FromLua::from_lua(value, lua)?;
+					return match &*name.to_str()? {
+						#(#variant_name_strs => Ok(Self::#variant_names),)*
+						other => Err(mlua::Error::FromLuaConversionError {
+							from: from,
+							to: #name_str.to_string(),
+							message: Some(format!("unknown variant '{}', expected one of: {}", other, #expected)),
+						}),
+					};
+				},
+			)
+		}
+		Repr::Table => unreachable!("handled by `table_repr_impls`"),
+		Repr::Integer => (
+			quote! {
+				return Ok(mlua::Value::Integer(self as mlua::Integer));
+			},
+			quote! {
+				let from: &'static str = value.type_name();
+				let integer: mlua::Integer = mlua::FromLua::from_lua(value, lua)?;
+				#(
+					if integer == Self::#variant_names as mlua::Integer {
+						return Ok(Self::#variant_names);
+					};
+				)*
+
+				let expected: Vec<String> = vec![
+					#(format!("{} ({})", Self::#variant_names as mlua::Integer, #variant_name_strs)),*
+				];
+				return Err(mlua::Error::FromLuaConversionError {
+					from: from,
+					to: #name_str.to_string(),
+					message: Some(format!("unknown value {}, expected one of: {}", integer, expected.join(", "))),
+				});
+			},
+		),
+	};
+
+	return Ok(quote! {
+		impl #impl_generics mlua::IntoLua for #name #ty_generics #where_clause {
+			fn into_lua(self, lua: &mlua::Lua) -> mlua::Result<mlua::Value> {
+				#into_lua
+			}
+		}
+
+		impl #impl_generics mlua::FromLua for #name #ty_generics #where_clause {
+			fn from_lua(value: mlua::Value, lua: &mlua::Lua) -> mlua::Result<Self> {
+				#from_lua
+			}
+		}
+	});
+}
+
+/// `IntoLua` and `FromLua` impls for `repr = "table"`: the variant name under `tag`,
+/// tuple payloads at `1`, `2`, ... and struct-like payloads under their names
+fn table_repr_impls(ast: &ItemEnum) -> darling::Result<TokenStream> {
+	let name: &Ident = &ast.ident;
+	let name_str: String = name.to_string();
+
+	let mut into_arms: Vec<TokenStream> = Vec::new();
+	let mut from_arms: Vec<TokenStream> = Vec::new();
+	let mut bounds: Vec<WherePredicate> = Vec::new();
+	let mut errors: darling::error::Accumulator = darling::Error::accumulator();
+	for variant in &ast.variants {
+		let variant_name: &Ident = &variant.ident;
+		let variant_name_str: String = variant_name.to_string();
+
+		// Where each payload field lives in the table, and how it is named in errors
+		let mut bindings: Vec<Ident> = Vec::new();
+		let mut keys: Vec<TokenStream> = Vec::new();
+		let mut key_names: Vec<String> = Vec::new();
+		for (index, field) in variant.fields.iter().enumerate() {
+			match &field.ident {
+				Some(field_name) => {
+					let key: String = field_name.to_string();
+					bindings.push(field_name.clone());
+					keys.push(quote! { #key });
+					key_names.push(key);
+				}
+				None => {
+					let key: Literal = Literal::i64_unsuffixed(index as i64 + 1);
+					bindings.push(format_ident!("field{}", index));
+					keys.push(quote! { #key });
+					key_names.push((index + 1).to_string());
+				}
+			};
+		}
+		let types: Vec<&Type> = variant.fields.iter().map(|field| &field.ty).collect();
+		if bindings.iter().any(|binding| binding == "tag") {
+			errors.push(
+				darling::Error::custom("a payload field named `tag` would hide the variant's tag")
+					.with_span(variant_name),
+			);
+			continue;
+		};
+		for ty in &types {
+			if generics::mentions_type_param(ty, &ast.generics) {
+				bounds.push(::syn::parse_quote! { #ty: mlua::IntoLua + mlua::FromLua });
+			};
+		}
+
+		let pattern: TokenStream = match &variant.fields {
+			Fields::Named(_) => quote! { Self::#variant_name { #(#bindings,)* } },
+			Fields::Unnamed(_) => quote! { Self::#variant_name(#(#bindings,)*) },
+			Fields::Unit => quote! { Self::#variant_name },
+		};
+		into_arms.push(quote! {
+			#pattern => {
+				table.raw_set("tag", #variant_name_str)?;
+				#(table.raw_set(#keys, #bindings)?;)*
+			}
+		});
+		from_arms.push(quote! {
+			#variant_name_str => {
+				#(
+					let #bindings: #types = table.get(#keys).map_err(|error: mlua::Error| {
+						return mlua::Error::runtime(format!(
+							"bad field '{}' of {}.{}: {}",
+							#key_names,
+							#name_str,
+							#variant_name_str,
+							error
+						));
+					})?;
+				)*
+				Ok(#pattern)
+			}
+		});
+	}
+	errors.finish()?;
+
+	let expected: String = ast
+		.variants
+		.iter()
+		.map(|variant: &Variant| variant.ident.to_string())
+		.collect::<Vec<String>>()
+		.join(", ");
+	let generics: ::syn::Generics = generics::with_bounds(&ast.generics, bounds);
+	let (impl_generics, _, where_clause) = generics.split_for_impl();
+	let (_, ty_generics, _) = ast.generics.split_for_impl();
+
+	return Ok(quote! {
+		impl #impl_generics mlua::IntoLua for #name #ty_generics #where_clause {
+			fn into_lua(self, lua: &mlua::Lua) -> mlua::Result<mlua::Value> {
+				let table: mlua::Table = lua.create_table()?;
+				match self {
+					#(#into_arms)*
+				};
+
+				return Ok(mlua::Value::Table(table));
+			}
+		}
+
+		impl #impl_generics mlua::FromLua for #name #ty_generics #where_clause {
+			fn from_lua(value: mlua::Value, lua: &mlua::Lua) -> mlua::Result<Self> {
+				let from: &'static str = value.type_name();
+				let table: mlua::Table = mlua::FromLua::from_lua(value, lua)?;
+				let tag: String = table.get("tag").map_err(|error: mlua::Error| {
+					return mlua::Error::runtime(format!("bad tag of {}: {}", #name_str, error));
+				})?;
+
+				return match tag.as_str() {
+					#(#from_arms)*
+					other => Err(mlua::Error::FromLuaConversionError {
+						from: from,
+						to: #name_str.to_string(),
+						message: Some(format!("unknown tag '{}', expected one of: {}", other, #expected)),
+					}),
+				};
+			}
+		}
+	});
+}
@@ -0,0 +1,134 @@
+use ::std::path::PathBuf;
+
+use ::proc_macro2::TokenStream;
+
+use ::quote::quote;
+
+use ::syn::Visibility;
+
+use ::darling::{Error, FromMeta};
+
+/// The table of the compiled crate's `Cargo.toml` holding the crate-wide default policy
+const METADATA_TABLE: &str = "[package.metadata.mlua_magic_macros]";
+
+/// Which items of a `#[structure]` or `#[implementation]` are exported to Lua
+#[derive(Debug, Clone, Copy, PartialEq, FromMeta)]
+pub enum Export {
+	/// Only `pub` and `pub(crate)` items
+	#[darling(rename = "pub")]
+	Pub,
+	/// Every item, whatever its visibility
+	#[darling(rename = "all")]
+	All,
+}
+
+impl Export {
+	/// The policy given on the item, or else the crate-wide default from `export` in
+	/// the `[package.metadata.mlua_magic_macros]` table of the crate's `Cargo.toml`,
+	/// or else `all`
+	pub fn resolve(policy: Option<Export>) -> darling::Result<Export> {
+		if let Some(policy) = policy {
+			return Ok(policy);
+		};
+
+		return Ok(manifest_default()?.unwrap_or(Export::All));
+	}
+
+	/// Makes `compile!` depend on `Cargo.toml`, so changing the crate-wide default
+	/// rebuilds the crate (and expands every item again). Once per `compile!` is
+	/// enough, whichever items read the default.
+	pub fn track_manifest() -> TokenStream {
+		if manifest_path().is_none() {
+			return quote! {};
+		};
+
+		return quote! {
+			const _: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"));
+		};
+	}
+
+	/// Whether an item is exported, with `#[lua(skip)]` and `#[lua(export)]` taking precedence
+	pub fn includes(self, vis: &Visibility, skip: bool, export: bool) -> bool {
+		if skip || export {
+			return export;
+		};
+
+		return match (self, vis) {
+			(Export::All, _) => true,
+			(Export::Pub, Visibility::Public(_)) => true,
+			(Export::Pub, Visibility::Restricted(restricted)) => {
+				restricted.in_token.is_none() && restricted.path.is_ident("crate")
+			}
+			(Export::Pub, Visibility::Inherited) => false,
+		};
+	}
+}
+
+/// The `Cargo.toml` of the crate being compiled (not of this one)
+fn manifest_path() -> Option<PathBuf> {
+	return ::std::env::var_os("CARGO_MANIFEST_DIR")
+		.map(|dir| PathBuf::from(dir).join("Cargo.toml"));
+}
+
+/// The policy set in the metadata table, if the manifest has one.
+///
+/// Anything in the table this crate does not know is an error rather than ignored,
+/// so a typo cannot silently fall back to `all`.
+fn manifest_default() -> darling::Result<Option<Export>> {
+	let Some(manifest) = manifest_path().and_then(|path| ::std::fs::read_to_string(path).ok())
+	else {
+		return Ok(None);
+	};
+	let manifest: ::toml::Table = manifest.parse().map_err(|e: ::toml::de::Error| {
+		Error::custom(format!("cannot read `Cargo.toml`: {}", e))
+	})?;
+
+	let metadata: Option<&::toml::Table> = manifest
+		.get("package")
+		.and_then(|package| package.get("metadata"))
+		.and_then(|metadata| metadata.as_table());
+	let Some(metadata) = metadata else {
+		return Ok(None);
+	};
+	if metadata.contains_key("mlua-magic-macros") {
+		return Err(Error::custom(format!(
+			"the metadata table of `mlua-magic-macros` is spelled `{}`",
+			METADATA_TABLE
+		)));
+	};
+	let Some(table) = metadata.get("mlua_magic_macros") else {
+		return Ok(None);
+	};
+	let Some(table) = table.as_table() else {
+		return Err(Error::custom(format!(
+			"`{}` must be a table",
+			METADATA_TABLE
+		)));
+	};
+
+	let mut policy: Option<Export> = None;
+	for (key, value) in table {
+		policy = match (key.as_str(), value.as_str()) {
+			("export", Some("pub")) => Some(Export::Pub),
+			("export", Some("all")) => Some(Export::All),
+			("export", found) => {
+				let found: String = match found {
+					Some(found) => format!("\"{}\"", found),
+					None => value.type_str().to_string(),
+				};
+				return Err(Error::custom(format!(
+					"`export` in `{}` must be \"pub\" or \"all\", found {}",
+					METADATA_TABLE, found
+				)));
+			}
+			(other, _) => {
+				return Err(Error::custom(format!(
+					"unknown key `{}` in `{}`, expected `export`",
+					other, METADATA_TABLE
+				)));
+			}
+		};
+	}
+
+	return Ok(policy);
+}
@@ -0,0 +1,77 @@
+use ::proc_macro2::{TokenStream, TokenTree};
+
+use ::quote::ToTokens;
+
+use ::syn::{GenericParam, Generics, WherePredicate};
+
+/// Whether `tokens` mention one of the type parameters declared in `generics`
+pub fn mentions_type_param(tokens: &impl ToTokens, generics: &Generics) -> bool {
+	let params: Vec<String> = generics
+		.type_params()
+		.map(|param| param.ident.to_string())
+		.collect();
+	if params.is_empty() {
+		return false;
+	};
+
+	fn walk(tokens: TokenStream, params: &[String]) -> bool {
+		return tokens.into_iter().any(|token: TokenTree| match token {
+			TokenTree::Ident(ident) => params.contains(&ident.to_string()),
+			TokenTree::Group(group) => walk(group.stream(), params),
+			_ => false,
+		});
+	}
+
+	return walk(tokens.to_token_stream(), &params);
+}
+
+/// Whether `tokens` mention `Self`
+pub fn mentions_self(tokens: &impl ToTokens) -> bool {
+	fn walk(tokens: TokenStream) -> bool {
+		return tokens.into_iter().any(|token: TokenTree| match token {
+			TokenTree::Ident(ident) => ident == "Self",
+			TokenTree::Group(group) => walk(group.stream()),
+			_ => false,
+		});
+	}
+
+	return walk(tokens.to_token_stream());
+}
+
+/// Clones `generics`, adding `T: 'static` for every type parameter plus `extra` predicates.
+///
+/// mlua stores every registered closure as `'static`, so the helpers generated
+/// for a generic type only exist for `'static` instantiations.
+pub fn with_bounds(generics: &Generics, extra: Vec<WherePredicate>) -> Generics {
+	let mut generics: Generics = generics.clone();
+	let static_bounds: Vec<WherePredicate> = generics
+		.params
+		.iter()
+		.filter_map(|param: &GenericParam| match param {
+			GenericParam::Type(type_param) => {
+				let ident = &type_param.ident;
+				Some(syn::parse_quote! { #ident: 'static })
+			}
+			_ => None,
+		})
+		.collect();
+
+	let where_clause: &mut syn::WhereClause = generics.make_where_clause();
+	where_clause.predicates.extend(static_bounds);
+	where_clause.predicates.extend(extra);
+
+	return generics;
+}
+
+/// Bounds `compile!` puts on every type parameter of a generic instantiation
+pub fn lua_value_bounds(generics: &Generics) -> Vec<WherePredicate> {
+	return generics
+		.type_params()
+		.map(|param| {
+			let ident = &param.ident;
+			return syn::parse_quote! {
+				#ident: mlua::IntoLua + mlua::FromLua + Clone + mlua::MaybeSend + 'static
+			};
+		})
+		.collect();
+}
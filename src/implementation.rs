@@ -0,0 +1,628 @@
+use ::proc_macro::TokenStream;
+
+use ::proc_macro2::{Ident, Span};
+
+use ::quote::format_ident;
+
+use ::syn::{
+	Expr, FnArg, ImplItem, ImplItemConst, ImplItemFn, ItemImpl, Pat, PatType, Path, ReturnType,
+	Type, spanned::Spanned,
+};
+
+use ::proc_macro2::TokenStream as TokenStream2;
+
+use ::quote::{quote, quote_spanned};
+
+use ::darling::{
+	Error, FromAttributes, FromMeta,
+	ast::NestedMeta,
+	util::{Override, SpannedValue},
+};
+
+use crate::callback;
+use crate::export::Export;
+use crate::throws::ThrowsArgs;
+
+/// Options given through `#[implementation(...)]`
+#[derive(Debug, Default, FromMeta)]
+pub struct ImplementationArgs {
+	/// Registers the block under `_to_mlua_methods_<tag>`, so a type can have several blocks
+	#[darling(default)]
+	pub tag: Option<Ident>,
+	/// `throws` for every fn of the block returning a `Result`
+	#[darling(default)]
+	pub throws: Option<Override<ThrowsArgs>>,
+	/// Which fns and consts are exported, `export = "pub"` or `export = "all"`
+	#[darling(default)]
+	pub export: Option<Export>,
+}
+
+/// Per-method options given through `#[lua(...)]` on a fn of an `#[implementation]` block
+#[derive(Debug, Default, FromAttributes)]
+#[darling(attributes(lua))]
+pub struct MethodArgs {
+	/// Registers the fn as a metamethod, e.g. `meta = "__call"`
+	#[darling(default)]
+	pub meta: Option<SpannedValue<String>>,
+	/// Registers the fn under another name; fns sharing a name become overloads
+	#[darling(default)]
+	pub name: Option<SpannedValue<String>>,
+	/// `Err(e)` is raised as a `{ kind, message, code }` table instead of returned
+	#[darling(default)]
+	pub throws: Option<Override<ThrowsArgs>>,
+	/// A fn taking `self` works on a copy instead of taking the value out of the userdata
+	#[darling(default)]
+	pub clone: bool,
+	/// The arguments are passed as one table keyed by parameter name, `Npc.new{ hp = 10 }`
+	#[darling(default)]
+	pub table_args: bool,
+	/// The returned collection or iterator is handed to Lua as a generic-for iterator
+	#[darling(default)]
+	pub iter: bool,
+	/// Leaves the item out of Lua
+	#[darling(default)]
+	pub skip: bool,
+	/// Exports the item even if the `export` policy leaves it out
+	#[darling(default)]
+	pub export: bool,
+}
+
+/// Per-parameter options given through `#[lua(...)]` on an argument of an exported fn
+#[derive(Debug, Default, FromAttributes)]
+#[darling(attributes(lua))]
+pub struct ParamArgs {
+	/// Value used when Lua passes `nil` or leaves the argument out
+	#[darling(default, with = darling::util::parse_expr::preserve_str_literal, map = Some)]
+	pub default: Option<Expr>,
+}
+
+/// Metamethods `#[lua(meta = "...")]` accepts, with their `mlua::MetaMethod` variant.
+///
+/// Some only exist for some Lua versions; mlua rejects those for the others.
+const META_METHODS: &[(&str, &str)] = &[
+	("__add", "Add"),
+	("__sub", "Sub"),
+	("__mul", "Mul"),
+	("__div", "Div"),
+	("__mod", "Mod"),
+	("__pow", "Pow"),
+	("__unm", "Unm"),
+	("__idiv", "IDiv"),
+	("__band", "BAnd"),
+	("__bor", "BOr"),
+	("__bxor", "BXor"),
+	("__bnot", "BNot"),
+	("__shl", "Shl"),
+	("__shr", "Shr"),
+	("__concat", "Concat"),
+	("__len", "Len"),
+	("__eq", "Eq"),
+	("__lt", "Lt"),
+	("__le", "Le"),
+	("__index", "Index"),
+	("__newindex", "NewIndex"),
+	("__call", "Call"),
+	("__tostring", "ToString"),
+	("__pairs", "Pairs"),
+	("__ipairs", "IPairs"),
+	("__iter", "Iter"),
+	("__close", "Close"),
+];
+
+/// The `mlua::MetaMethod` variant for a metamethod name such as `"__call"`
+pub fn meta_method(name: &str) -> Option<Ident> {
+	return META_METHODS
+		.iter()
+		.find(|(lua_name, _)| *lua_name == name)
+		.map(|(_, variant)| format_ident!("{}", variant));
+}
+
+/// The name a fn is exported under: its metamethod, its `name`, or its own name
+pub fn lua_name(fn_item: &ImplItemFn, args: &MethodArgs) -> String {
+	return args
+		.meta
+		.as_deref()
+		.or(args.name.as_deref())
+		.cloned()
+		.unwrap_or_else(|| fn_item.sig.ident.to_string());
+}
+
+/// Where the Lua name of a fn is written: its `meta` or `name`, or its own name
+pub fn lua_name_span(fn_item: &ImplItemFn, args: &MethodArgs) -> Span {
+	return args
+		.meta
+		.as_ref()
+		.or(args.name.as_ref())
+		.map_or(fn_item.sig.ident.span(), SpannedValue::span);
+}
+
+/// Names exported by more than one fn of the block, which are dispatched as overloads
+pub fn overloaded_names(ast: &ItemImpl, export: Export) -> Vec<String> {
+	let mut seen: Vec<String> = Vec::new();
+	let mut overloaded: Vec<String> = Vec::new();
+	for item in &ast.items {
+		let ImplItem::Fn(fn_item) = item else {
+			continue;
+		};
+		let Ok(args) = MethodArgs::from_attributes(&fn_item.attrs) else {
+			continue;
+		};
+		if args.meta.is_some() || !export.includes(&fn_item.vis, args.skip, args.export) {
+			continue;
+		};
+
+		let lua_name: String = lua_name(fn_item, &args);
+		if seen.contains(&lua_name) {
+			if !overloaded.contains(&lua_name) {
+				overloaded.push(lua_name);
+			};
+		} else {
+			seen.push(lua_name);
+		};
+	}
+
+	return overloaded;
+}
+
+/// One fn of an overloaded name
+pub struct Overload {
+	pub pats: Vec<TokenStream2>,
+	pub tys: Vec<TokenStream2>,
+	/// How many of `tys` Lua has to pass, leaving out those with a `default`
+	pub required: usize,
+	/// Runs the fn, returning its values as an `mlua::MultiValue`
+	pub body: TokenStream2,
+	/// `name(self, f32)`, for the error when no overload matches
+	pub signature: String,
+}
+
+/// The signature of `fn_item` as shown to Lua users, `new(f32, f32)`
+pub fn overload_signature(lua_name: &str, fn_item: &ImplItemFn) -> String {
+	let params: Vec<String> = fn_item
+		.sig
+		.inputs
+		.iter()
+		.map(|arg: &FnArg| match arg {
+			FnArg::Receiver(_) => "self".to_string(),
+			FnArg::Typed(pat_type) => {
+				let ty: &Type = &pat_type.ty;
+				quote! { #ty }
+					.to_string()
+					.replace(" <", "<")
+					.replace("< ", "<")
+					.replace(" >", ">")
+					.replace(" ,", ",")
+					.replace("& ", "&")
+			}
+		})
+		.collect();
+
+	return format!("{}({})", lua_name, params.join(", "));
+}
+
+/// A single function registered under `lua_name`, calling the first overload whose
+/// parameters convert from the Lua arguments. Overloads taking exactly as many
+/// arguments as given are tried first, then those leaving defaulted ones out.
+pub fn overload_dispatch(lua_name: &str, overloads: &[Overload]) -> TokenStream2 {
+	let attempt = |overload: &Overload| -> TokenStream2 {
+		let pats: &Vec<TokenStream2> = &overload.pats;
+		let tys: &Vec<TokenStream2> = &overload.tys;
+		let body: &TokenStream2 = &overload.body;
+		return quote! {
+			if let Ok((#(#pats,)*)) = <(#(#tys,)*) as mlua::FromLuaMulti>::from_lua_multi(args.clone(), lua) {
+				#body
+			};
+		};
+	};
+	let exact: Vec<TokenStream2> = overloads
+		.iter()
+		.map(|overload| {
+			let arity: usize = overload.tys.len();
+			let attempt: TokenStream2 = attempt(overload);
+			return quote! {
+				if args.len() == #arity {
+					#attempt
+				};
+			};
+		})
+		.collect();
+	let shortened: Vec<TokenStream2> = overloads
+		.iter()
+		.filter(|overload| overload.required < overload.tys.len())
+		.map(|overload| {
+			let (required, arity): (usize, usize) = (overload.required, overload.tys.len());
+			let attempt: TokenStream2 = attempt(overload);
+			return quote! {
+				if (#required..#arity).contains(&args.len()) {
+					#attempt
+				};
+			};
+		})
+		.collect();
+	let signatures: String = overloads
+		.iter()
+		.map(|overload| overload.signature.as_str())
+		.collect::<Vec<&str>>()
+		.join(", ");
+
+	return quote! {
+		methods.add_function(#lua_name, |lua, args: mlua::MultiValue| {
+			#(#exact)*
+			#(#shortened)*
+
+			let given: Vec<&str> = args.iter().map(|value: &mlua::Value| value.type_name()).collect();
+			return Err(mlua::Error::runtime(format!(
+				"no overload of '{}' takes ({}), expected one of: {}",
+				#lua_name,
+				given.join(", "),
+				#signatures
+			)));
+		});
+	};
+}
+
+/// Whether a parameter carries `#[lua(default = ...)]`
+pub fn has_default(pat_type: &PatType) -> bool {
+	return ParamArgs::from_attributes(&pat_type.attrs).is_ok_and(|args| args.default.is_some());
+}
+
+/// `#[lua(skip)]` and `#[lua(export)]` on an associated const
+pub fn parse_const_args(const_item: &ImplItemConst) -> darling::Result<MethodArgs> {
+	let args: MethodArgs = MethodArgs::from_attributes(&const_item.attrs)?;
+
+	if args.skip && args.export {
+		return Err(
+			Error::custom("`skip` cannot be combined with `export`").with_span(&const_item.ident)
+		);
+	};
+
+	return Ok(args);
+}
+
+pub fn parse_method_args(fn_item: &ImplItemFn) -> darling::Result<MethodArgs> {
+	let args: MethodArgs = MethodArgs::from_attributes(&fn_item.attrs)?;
+
+	if args.meta.is_some() && args.throws.is_some() {
+		return Err(
+			Error::custom("`throws` cannot be combined with `meta`").with_span(&fn_item.sig.ident)
+		);
+	};
+	if args.skip && args.export {
+		return Err(
+			Error::custom("`skip` cannot be combined with `export`").with_span(&fn_item.sig.ident)
+		);
+	};
+	if args.meta.is_some() && args.name.is_some() {
+		return Err(
+			Error::custom("`name` cannot be combined with `meta`").with_span(&fn_item.sig.ident)
+		);
+	};
+	if args.iter && (args.throws.is_some() || fn_item.sig.asyncness.is_some()) {
+		return Err(
+			Error::custom("`iter` cannot be combined with `throws` or async fns")
+				.with_span(&fn_item.sig.ident),
+		);
+	};
+	if let Some(meta) = &args.meta
+		&& meta_method(meta).is_none()
+	{
+		let known: Vec<&str> = META_METHODS.iter().map(|(lua_name, _)| *lua_name).collect();
+		return Err(Error::custom(format!(
+			"unknown metamethod `{}`, expected one of: {}",
+			meta.as_str(),
+			known.join(", ")
+		))
+		.with_span(&fn_item.sig.ident));
+	};
+
+	return Ok(args);
+}
+
+/// Binds an argument of type `ty` to Lua parameters named after `name`, returning
+/// the expression that rebuilds it for the call. `params` gets the pattern and
+/// Lua-facing type of each parameter.
+///
+/// Tuples have no single Lua value, so each element becomes a parameter of its own:
+/// `(x, y): (f32, f32)` is called from Lua as `f(x, y)`. References are borrowed
+/// from the Lua value: `&str` and `&[u8]` from a string, `&T` and `&mut T` from a
+/// `T` userdata (failing if it is already borrowed), `&[T]` from a sequence.
+/// `impl Fn(..) -> mlua::Result<R>` and `&dyn Fn(..) -> mlua::Result<R>` (or `FnMut`/`FnOnce`)
+/// take a Lua function.
+pub fn bind_arg(name: Ident, ty: &Type, params: &mut Vec<(TokenStream2, Type)>) -> TokenStream2 {
+	if let Some(signature) = callback::fn_signature(ty) {
+		let call: TokenStream2 = callback::bind_callback(&name, ty, signature);
+		params.push((quote! { #name }, syn::parse_quote! { mlua::Function }));
+		return call;
+	};
+
+	match ty {
+		Type::Tuple(tuple) => {
+			let elems: Vec<TokenStream2> = tuple
+				.elems
+				.iter()
+				.enumerate()
+				.map(|(index, elem)| bind_arg(format_ident!("{}_{}", name, index), elem, params))
+				.collect();
+
+			return quote! { (#(#elems,)*) };
+		}
+		Type::Reference(reference) => {
+			let target: &Type = &reference.elem;
+			let is_str: bool = matches!(target, Type::Path(path) if path.path.is_ident("str"));
+			let (lua_ty, call): (Type, TokenStream2) = match (target, &reference.mutability) {
+				(Type::Slice(slice), None) => {
+					let elem: &Type = &slice.elem;
+					if matches!(elem, Type::Path(path) if path.path.is_ident("u8")) {
+						(
+							syn::parse_quote! { mlua::BorrowedBytes<'static> },
+							quote! { &*#name },
+						)
+					} else {
+						(syn::parse_quote! { Vec<#elem> }, quote! { &#name })
+					}
+				}
+				(_, None) if is_str => (
+					syn::parse_quote! { mlua::BorrowedStr<'static> },
+					quote! { &*#name },
+				),
+				(_, None) => (
+					syn::parse_quote! { mlua::UserDataRef<#target> },
+					quote! { &*#name },
+				),
+				(_, Some(_)) => {
+					params.push((
+						quote! { mut #name },
+						syn::parse_quote! { mlua::UserDataRefMut<#target> },
+					));
+					return quote! { &mut *#name };
+				}
+			};
+
+			params.push((quote! { #name }, lua_ty));
+			return call;
+		}
+		_ => {
+			params.push((quote! { #name }, ty.clone()));
+			return quote! { #name };
+		}
+	};
+}
+
+/// Like `bind_arg`, for a parameter that may carry `#[lua(default = ...)]`.
+///
+/// A defaulted parameter is an `Option<T>` on the Lua side, filled in before the call.
+pub fn bind_param(
+	name: Ident,
+	pat_type: &PatType,
+	params: &mut Vec<(TokenStream2, Type)>,
+) -> darling::Result<TokenStream2> {
+	let args: ParamArgs = ParamArgs::from_attributes(&pat_type.attrs)?;
+	let ty: &Type = &pat_type.ty;
+	callback::validate(ty)?;
+
+	let Some(default) = args.default else {
+		return Ok(bind_arg(name, ty, params));
+	};
+	if matches!(ty, Type::Reference(_) | Type::Tuple(_)) || callback::fn_signature(ty).is_some() {
+		return Err(Error::custom(
+			"`default` is only supported on owned, non-tuple, non-callback arguments",
+		)
+		.with_span(ty));
+	};
+
+	params.push((quote! { #name }, syn::parse_quote! { Option<#ty> }));
+	return Ok(quote! {
+		#name.unwrap_or_else(|| ::std::convert::Into::<#ty>::into(#default))
+	});
+}
+
+/// The key a parameter is looked up under with `table_args`
+pub fn table_key(pat_type: &PatType) -> darling::Result<String> {
+	return match &*pat_type.pat {
+		Pat::Ident(pat_ident) if !matches!(&*pat_type.ty, Type::Tuple(_)) => {
+			Ok(pat_ident.ident.to_string())
+		}
+		_ => Err(
+			Error::custom("`table_args` needs every argument to be a plain name")
+				.with_span(&pat_type.pat),
+		),
+	};
+}
+
+/// Statements unpacking `args_table` into the bound parameters, with errors naming
+/// the key and the fn. Unknown keys are rejected, so typos do not go unnoticed.
+pub fn unpack_table_args(
+	fn_name: &str,
+	keys: &[String],
+	params: &[(TokenStream2, Type)],
+) -> TokenStream2 {
+	let (pats, tys): (Vec<&TokenStream2>, Vec<&Type>) =
+		params.iter().map(|(pat, ty)| (pat, ty)).unzip();
+
+	return quote! {
+		let keys: &[&str] = &[#(#keys),*];
+		for pair in args_table.pairs::<mlua::Value, mlua::Value>() {
+			let (key, _): (mlua::Value, mlua::Value) = pair?;
+			let known: bool = key
+				.as_string()
+				.and_then(|key| key.to_str().ok())
+				.is_some_and(|key| keys.contains(&&*key));
+			if !known {
+				return Err(mlua::Error::runtime(format!(
+					"unknown argument '{}' to '{}'",
+					key.to_string()?,
+					#fn_name,
+				)));
+			};
+		}
+		#(
+			let #pats: #tys = args_table.get::<#tys>(#keys).map_err(|error: mlua::Error| {
+				return mlua::Error::runtime(format!("bad argument '{}' to '{}': {}", #keys, #fn_name, error));
+			})?;
+		)*
+	};
+}
+
+/// Removes our `#[lua(...)]` helper attributes so the re-emitted impl still compiles
+pub fn strip_method_attrs(ast: &mut ItemImpl) -> () {
+	for item in ast.items.iter_mut() {
+		if let ImplItem::Const(const_item) = item {
+			const_item.attrs.retain(|attr| !attr.path().is_ident("lua"));
+		};
+		if let ImplItem::Fn(fn_item) = item {
+			fn_item.attrs.retain(|attr| !attr.path().is_ident("lua"));
+			for input in fn_item.sig.inputs.iter_mut() {
+				if let FnArg::Typed(pat_type) = input {
+					pat_type.attrs.retain(|attr| !attr.path().is_ident("lua"));
+				};
+			}
+		};
+	}
+}
+
+/// The helper a block registers its methods under
+pub fn methods_helper(tag: Option<&Ident>) -> Ident {
+	return tagged(format_ident!("_to_mlua_methods"), tag);
+}
+
+/// The helper a block registers its fields under (the fns that need a field getter, see `throws`)
+pub fn method_fields_helper(tag: Option<&Ident>) -> Ident {
+	return tagged(format_ident!("_to_mlua_method_fields"), tag);
+}
+
+/// A const the block's methods helper reads, so it is used exactly when the helper is.
+/// A block nothing registers (say, a tag missing from `compile!(tags(...))`) then
+/// warns that `<tag>_missing_from_compile_tags` (or `missing_from_compile_methods`)
+/// is never used. Calling the helper by hand from an `impl mlua::UserData` counts
+/// as registering it, and `#[allow(dead_code)]` on the block silences the warning.
+///
+/// The const is spanned on the tag, or on `span` for an untagged block: lints are not
+/// reported on code spanned inside a macro.
+pub fn registration_check(tag: Option<&Ident>, span: Span) -> Ident {
+	return match tag {
+		Some(tag) => format_ident!("{}_missing_from_compile_tags", tag, span = tag.span()),
+		None => format_ident!("missing_from_compile_methods", span = span),
+	};
+}
+
+fn tagged(helper: Ident, tag: Option<&Ident>) -> Ident {
+	return match tag {
+		Some(tag) => format_ident!("{}_{}", helper, tag),
+		None => helper,
+	};
+}
+
+/// A hidden associated const claiming `lua_name` for the type.
+///
+/// Every block declares one per exported name, so two blocks exporting the
+/// same name fail with a duplicate definition error. The const is spanned on
+/// `span`, the item exporting the name, so the error points at both items.
+///
+/// The name is escaped so distinct Lua names never share a const: `_` becomes
+/// `__` and any other character that is not alphanumeric `_u<hex>_`, so
+/// `a_b` claims `__mlua_export_a__b` and `a-b` claims `__mlua_export_a_u2d_b`.
+pub fn export_marker(lua_name: &str, span: Span) -> Ident {
+	let mut escaped: String = String::new();
+	for c in lua_name.chars() {
+		match c {
+			'_' => escaped.push_str("__"),
+			c if c.is_ascii_alphanumeric() => escaped.push(c),
+			c => escaped.push_str(&format!("_u{:x}_", c as u32)),
+		};
+	}
+
+	return format_ident!("__mlua_export_{}", escaped, span = span);
+}
+
+/// The item declaring an export marker, for an inherent impl of the type
+pub fn export_marker_item(export_marker: &Ident) -> TokenStream2 {
+	return quote_spanned! { export_marker.span() =>
+		#[doc(hidden)]
+		#[allow(non_upper_case_globals)]
+		pub const #export_marker: () = ();
+	};
+}
+
+/// Checks that the arguments and the return value of an exported fn convert to and
+/// from Lua, spanned on the types themselves: a type that cannot is reported where it
+/// is written, not on the attribute. `&T` and `&mut T` arguments need `T: UserData`.
+///
+/// `value_ty` is what a `throws` fn returns on success, and `skip` leaves out types
+/// checked through the block's bounds instead (those depending on type parameters).
+pub fn conversion_checks(
+	fn_item: &ImplItemFn,
+	value_ty: Option<&Type>,
+	iter: bool,
+	skip: impl Fn(&Type) -> bool,
+) -> Vec<TokenStream2> {
+	let mut checks: Vec<TokenStream2> = Vec::new();
+	for input in &fn_item.sig.inputs {
+		if let FnArg::Typed(pat_type) = input {
+			argument_checks(&pat_type.ty, &skip, &mut checks);
+		};
+	}
+
+	let output: Option<&Type> = match (&fn_item.sig.output, value_ty) {
+		_ if iter => None,
+		(_, Some(value_ty)) => Some(value_ty),
+		(ReturnType::Type(_, output), None) => Some(output),
+		(ReturnType::Default, None) => None,
+	};
+	if let Some(output) = output
+		&& !matches!(output, Type::ImplTrait(_))
+		&& !skip(output)
+	{
+		checks.push(quote_spanned! { output.span() =>
+			let _ = <#output as mlua::IntoLuaMulti>::into_lua_multi;
+		});
+	};
+
+	return checks;
+}
+
+fn argument_checks(ty: &Type, skip: &impl Fn(&Type) -> bool, checks: &mut Vec<TokenStream2>) -> () {
+	if skip(ty) || callback::fn_signature(ty).is_some() {
+		return;
+	};
+
+	match ty {
+		Type::Tuple(tuple) => {
+			for elem in &tuple.elems {
+				argument_checks(elem, skip, checks);
+			}
+		}
+		Type::Reference(reference) => {
+			let target: &Type = &reference.elem;
+			if matches!(target, Type::Slice(_))
+				|| matches!(target, Type::Path(path) if path.path.is_ident("str"))
+			{
+				return;
+			};
+			checks.push(quote_spanned! { target.span() =>
+				let _ = <#target as mlua::UserData>::register;
+			});
+		}
+		Type::ImplTrait(_) => {}
+		_ => checks.push(quote_spanned! { ty.span() =>
+			let _ = <#ty as mlua::FromLua>::from_lua;
+		}),
+	};
+}
+
+/// The format string of `impl Display` (`"{}"`) and `impl Debug` (`"{:?}"`) blocks,
+/// which are exported as `__tostring` instead of as their `fmt` fn
+pub fn tostring_format(trait_path: &Path) -> Option<&'static str> {
+	let trait_name: String = trait_path.segments.last()?.ident.to_string();
+
+	return match trait_name.as_str() {
+		"Display" => Some("{}"),
+		"Debug" => Some("{:?}"),
+		_ => None,
+	};
+}
+
+pub fn parse_implementation_args(attr: TokenStream) -> darling::Result<ImplementationArgs> {
+	let items: Vec<NestedMeta> = NestedMeta::parse_meta_list(attr.into())?;
+
+	return ImplementationArgs::from_list(&items);
+}
@@ -0,0 +1,134 @@
+use ::proc_macro2::{TokenStream, TokenTree};
+
+use ::quote::{ToTokens, quote};
+
+use ::syn::{GenericArgument, PathArguments, ReturnType, Type, TypeParamBound, WherePredicate};
+
+/// Whether the value returned by a `&self`/`&mut self` fn may borrow from `self`,
+/// in which case the userdata stays borrowed until the iterator is done.
+/// `impl Trait` captures every lifetime in scope, so it always counts.
+pub fn borrows(output: &ReturnType) -> bool {
+	fn scan(tokens: TokenStream) -> bool {
+		return tokens.into_iter().any(|token: TokenTree| match token {
+			TokenTree::Ident(ident) => ident == "impl",
+			TokenTree::Punct(punct) => punct.as_char() == '&' || punct.as_char() == '\'',
+			TokenTree::Group(group) => scan(group.stream()),
+			TokenTree::Literal(_) => false,
+		});
+	}
+
+	return match output {
+		ReturnType::Default => false,
+		ReturnType::Type(_, ty) => scan(ty.to_token_stream()),
+	};
+}
+
+/// The bound letting the items of `output` reach Lua: on `X` for
+/// `impl Iterator<Item = X>`, otherwise on `<output as IntoIterator>::Item`
+pub fn item_bound(output: &Type) -> WherePredicate {
+	let item: Option<&Type> = match output {
+		Type::ImplTrait(impl_trait) => impl_trait.bounds.iter().find_map(|bound| {
+			let TypeParamBound::Trait(trait_bound) = bound else {
+				return None;
+			};
+			let PathArguments::AngleBracketed(args) = &trait_bound.path.segments.last()?.arguments
+			else {
+				return None;
+			};
+
+			return args
+				.args
+				.iter()
+				.find_map(|arg: &GenericArgument| match arg {
+					GenericArgument::AssocType(assoc) if assoc.ident == "Item" => Some(&assoc.ty),
+					_ => None,
+				});
+		}),
+		_ => None,
+	};
+
+	return match item {
+		Some(item) => ::syn::parse_quote! { #item: mlua::IntoLuaMulti },
+		None => ::syn::parse_quote! {
+			<#output as ::std::iter::IntoIterator>::Item: mlua::IntoLuaMulti
+		},
+	};
+}
+
+/// Statements borrowing `user_data` as `this` for as long as the iterator lives.
+///
+/// The guard is boxed and dropped after the iterator (see `body`), so the
+/// `'static` reference never outlives the borrow it comes from.
+pub fn guard(mutable: bool) -> TokenStream {
+	return if mutable {
+		quote! {
+			let mut guard: Box<mlua::UserDataRefMut<Self>> = Box::new(user_data.borrow_mut::<Self>()?);
+			// SAFETY: the userdata is borrowed until `guard` is dropped, which only happens after the iterator
+			let this: &'static mut Self = unsafe { &mut *(&mut **guard as *mut Self) };
+		}
+	} else {
+		quote! {
+			let guard: Box<mlua::UserDataRef<Self>> = Box::new(user_data.borrow::<Self>()?);
+			// SAFETY: the userdata is borrowed until `guard` is dropped, which only happens after the iterator
+			let this: &'static Self = unsafe { &*(&**guard as *const Self) };
+		}
+	};
+}
+
+/// Wraps the collection returned by `invoke` in a generic-for iterator.
+///
+/// The iterator state (and the borrow of `self`, if any) is released as soon as the
+/// iterator runs out, or when a `for` loop is left early through its closing value.
+pub fn body(invoke: &TokenStream, borrows: bool) -> TokenStream {
+	let guard: TokenStream = if borrows {
+		quote! { guard }
+	} else {
+		quote! { () }
+	};
+
+	return quote! {
+		let items = ::std::iter::IntoIterator::into_iter(#invoke);
+		// The iterator comes first, so it is dropped before the guard it may borrow from
+		let mut state = Some((items, #guard));
+		let next: mlua::Function = lua.create_function_mut(move |lua, (close, _): (Option<bool>, mlua::Value)| {
+			let item = match state.as_mut() {
+				Some((items, _)) if close != Some(true) => items.next(),
+				_ => None,
+			};
+			return match item {
+				Some(item) => mlua::IntoLuaMulti::into_lua_multi(item, lua),
+				None => {
+					state = None;
+					Ok(mlua::MultiValue::new())
+				}
+			};
+		})?;
+		return lua_iterator(&lua, next);
+	};
+}
+
+/// Defines `lua_iterator`, which returns `next` with a closing value for Lua 5.4's
+/// generic `for`, so that `break` releases the iterator state right away
+pub fn support_fn() -> TokenStream {
+	return quote! {
+		fn lua_iterator(lua: &mlua::Lua, next: mlua::Function) -> mlua::Result<mlua::MultiValue> {
+			let factory: mlua::Function = match lua.named_registry_value::<Option<mlua::Function>>("__magic_iter")? {
+				Some(factory) => factory,
+				None => {
+					let factory: mlua::Function = lua
+						.load(r#"
+							local next = ...
+							local closer = setmetatable({}, { __close = function() next(true) end })
+							return next, nil, nil, closer
+						"#)
+						.set_name("=iter")
+						.into_function()?;
+					lua.set_named_registry_value("__magic_iter", &factory)?;
+					factory
+				}
+			};
+
+			return factory.call(next);
+		}
+	};
+}
@@ -1,5 +1,17 @@
+mod callback;
 mod compile;
+mod enumeration;
+mod export;
+mod generics;
+mod implementation;
+mod iter;
 mod load;
+mod meta;
+mod ops;
+mod projection;
+mod statics;
+mod structure;
+mod throws;
 
 extern crate proc_macro;
 
@@ -10,9 +22,9 @@ use ::proc_macro::TokenStream;
 use ::proc_macro2;
 use ::proc_macro2::Ident;
 
-use ::quote::{format_ident, quote};
+use ::quote::{format_ident, quote, quote_spanned};
 
-use ::syn::{Fields, Pat, TypePath, parse_macro_input};
+use ::syn::{Fields, TypePath, parse_macro_input};
 
 /// Implements a helper function `_to_mlua_fields` for a Rust struct,
 /// enabling automatic registration of named fields with `mlua::UserData`.
@@ -23,23 +35,52 @@ use ::syn::{Fields, Pat, TypePath, parse_macro_input};
 /// are visible in Lua as userdata fields.
 ///
 /// # Behavior
-/// * Public and private named fields are exported as readable fields in Lua.
+/// * Public and private named fields are exported as readable and writable fields in Lua.
 /// * Getter methods are automatically generated via `add_field_method_get`.
+/// * Setter methods are automatically generated via `add_field_method_set`.
 /// * Fields must implement `Clone` for successful conversion to Lua values.
+/// * Generic structs keep their generics; fields whose type depends on a type
+///   parameter require it to convert to and from Lua.
 ///
-/// # Limitations
-/// * Only structs with **named fields** are currently supported.
-/// * Setter support is not yet implemented.
+/// # Field attributes
+/// Individual fields can be tuned with `#[lua(...)]`:
+/// * `#[lua(skip)]` — the field is not exported at all.
+/// * `#[lua(rename = "displayName")]` — the field is exported under another name.
+/// * `#[lua(readonly)]` — only a getter is generated.
+/// * `#[lua(writeonly)]` — only a setter is generated.
+/// * `#[lua(project)]` — the getter returns a live view of the field instead of a
+///   copy, so `player.pos.x = 5` or `player.inventory:add(item)` change the
+///   original value. The field type must be compiled with `compile!` as well.
+///   Methods taking `self` by value refuse to run through the view.
+///
+/// The attributes are removed from the struct that is emitted again.
+///
+/// # Export policy
+/// `#[structure(export = "pub")]` exports only `pub` and `pub(crate)` fields;
+/// `#[lua(export)]` exports a field regardless. Without `export`, the crate-wide
+/// default from `export` under `[package.metadata.mlua_magic_macros]` in `Cargo.toml`
+/// applies, which defaults to `"all"`.
+///
+/// # Tuple and unit structs
+/// Fields of tuple structs are reached by position, `color[1]`, `color[2]`, ...
+/// (1-based, like Lua sequences). With `#[structure(positional = "named")]` they
+/// are exported as `_0`, `_1`, ... instead. A `rename` always gives the field a
+/// name. Unit structs export no fields.
 ///
 /// # Usage
 /// Apply the macro directly to the struct definition:
 ///
 /// ```ignore
-/// #[derive(Clone, Copy, Default)]
+/// #[derive(Clone, Default)]
 /// #[mlua_magic_macros::structure]
 /// struct Player {
+///     #[lua(readonly)]
+///     id: u64,
+///     #[lua(rename = "displayName")]
 ///     name: String,
 ///     hp: i32,
+///     #[lua(skip)]
+///     dirty: bool,
 /// }
 ///
 /// // Later, compile userdata:
@@ -50,7 +91,7 @@ use ::syn::{Fields, Pat, TypePath, parse_macro_input};
 /// Lua scripts may access the fields:
 ///
 /// ```lua
-/// print(player.name)
+/// print(player.displayName)
 /// print(player.hp)
 /// ```
 ///
@@ -62,39 +103,226 @@ use ::syn::{Fields, Pat, TypePath, parse_macro_input};
 /// This simplifies mlua integration by reducing boilerplate and
 /// ensuring a consistent interface between Rust types and Lua scripts.
 #[proc_macro_attribute]
-pub fn structure(_attr: TokenStream, item: TokenStream) -> TokenStream {
-	let ast: syn::ItemStruct = parse_macro_input!(item as syn::ItemStruct);
-	let name: &Ident = &ast.ident;
+pub fn structure(attr: TokenStream, item: TokenStream) -> TokenStream {
+	let mut ast: syn::ItemStruct = parse_macro_input!(item as syn::ItemStruct);
+	let structure_args: structure::StructureArgs = match structure::parse_structure_args(attr) {
+		Ok(structure_args) => structure_args,
+		Err(e) => return e.write_errors().into(),
+	};
+	let export: export::Export = match export::Export::resolve(structure_args.export) {
+		Ok(export) => export,
+		Err(e) => return e.with_span(&ast.ident).write_errors().into(),
+	};
 
 	// TODO: Add type validation?
 	let mut user_data_fields: Vec<proc_macro2::TokenStream> = Vec::new();
+	let mut projected_fields: Vec<proc_macro2::TokenStream> = Vec::new();
+	let mut index_getters: Vec<proc_macro2::TokenStream> = Vec::new();
+	let mut index_setters: Vec<proc_macro2::TokenStream> = Vec::new();
+	let mut positional_span: Option<proc_macro2::Span> = None;
+	let mut field_bounds: Vec<syn::WherePredicate> = Vec::new();
+	let mut errors: darling::error::Accumulator = darling::Error::accumulator();
 
-	for field in &ast.fields {
-		let field_name: &Ident = field.ident.as_ref().expect("Field must have a name");
-		let field_name_str: String = field_name.to_string();
-		let field_ty: &syn::Type = &field.ty;
+	for (index, field) in ast.fields.iter().enumerate() {
+		let Some(field_args) = errors.handle(structure::parse_field_args(field)) else {
+			continue;
+		};
+		if !export.includes(&field_args.vis, field_args.skip, field_args.export) {
+			continue;
+		};
+
+		let field_member: syn::Member = match &field.ident {
+			Some(ident) => syn::Member::Named(ident.clone()),
+			None => syn::Member::Unnamed(index.into()),
+		};
+		let field_ty: &syn::Type = &field_args.ty;
 
-		user_data_fields.push(quote! {
-			fields.add_field_method_get(#field_name_str, |_, this| {
-				return Ok(this.#field_name.clone());
+		// Fields that depend on a type parameter only convert for some instantiations
+		if generics::mentions_type_param(field_ty, &ast.generics) {
+			if field_args.project {
+				errors.push(
+					darling::Error::custom(
+						"`project` is not supported on fields whose type depends on a type parameter",
+					)
+					.with_span(field),
+				);
+				continue;
+			};
+			field_bounds.push(syn::parse_quote! {
+				#field_ty: mlua::IntoLua + mlua::FromLua + Clone
 			});
-		});
+		};
+
+		// Tuple fields without a name are reached as `value[1]`, `value[2]`, ...
+		let Some(field_name_str) = field_args.lua_name(index, structure_args.positional) else {
+			let lua_index: proc_macro2::Literal =
+				proc_macro2::Literal::i64_unsuffixed(index as i64 + 1);
+			positional_span.get_or_insert(syn::spanned::Spanned::span(&field.ty));
+
+			if !field_args.writeonly {
+				if field_args.project {
+					index_getters.push(quote! {
+						Some(#lua_index) => {
+							let user_data: mlua::AnyUserData = user_data.clone();
+							let place = move |f: &mut dyn FnMut(&mut #field_ty)| -> mlua::Result<()> {
+								let mut this: mlua::UserDataRefMut<Self> = user_data.borrow_mut::<Self>()?;
+								f(&mut this.#field_member);
+								return Ok(());
+							};
+							return <#field_ty>::_to_mlua_projection(lua, place).map(mlua::Value::UserData);
+						}
+					});
+				} else {
+					index_getters.push(quote! {
+						Some(#lua_index) => {
+							return mlua::IntoLua::into_lua(user_data.borrow::<Self>()?.#field_member.clone(), lua);
+						}
+					});
+				};
+			};
+
+			if !field_args.readonly {
+				index_setters.push(quote! {
+					Some(#lua_index) => {
+						this.#field_member = <#field_ty as mlua::FromLua>::from_lua(value, lua)?;
+						return Ok(());
+					}
+				});
+			};
+
+			continue;
+		};
+
+		if !field_args.writeonly {
+			if field_args.project {
+				// Hand out a live view that borrows through this userdata on every access
+				user_data_fields.push(quote! {
+					fields.add_field_function_get(#field_name_str, |lua, user_data: mlua::AnyUserData| {
+						let place = move |f: &mut dyn FnMut(&mut #field_ty)| -> mlua::Result<()> {
+							let mut this: mlua::UserDataRefMut<Self> = user_data.borrow_mut::<Self>()?;
+							f(&mut this.#field_member);
+							return Ok(());
+						};
+						return <#field_ty>::_to_mlua_projection(lua, place);
+					});
+				});
+				projected_fields.push(quote! {
+					fields.add_field_method_get(#field_name_str, move |lua, projection| {
+						let parent: P = place(projection).clone();
+						let place = move |f: &mut dyn FnMut(&mut #field_ty)| -> mlua::Result<()> {
+							return parent(&mut |this: &mut Self| f(&mut this.#field_member));
+						};
+						return <#field_ty>::_to_mlua_projection(lua, place);
+					});
+				});
+			} else {
+				user_data_fields.push(quote! {
+					fields.add_field_method_get(#field_name_str, |_, this| {
+						return Ok(this.#field_member.clone());
+					});
+				});
+				projected_fields.push(quote! {
+					fields.add_field_method_get(#field_name_str, move |_, projection| {
+						let mut value: Option<#field_ty> = None;
+						place(projection)(&mut |this: &mut Self| {
+							value = Some(this.#field_member.clone());
+						})?;
+						return Ok(value);
+					});
+				});
+			};
+		};
 
-		user_data_fields.push(quote! {
-			fields.add_field_method_set(#field_name_str, |_, this, val: #field_ty| {
-				this.#field_name = val;
-				return Ok(());
+		if !field_args.readonly {
+			user_data_fields.push(quote! {
+				fields.add_field_method_set(#field_name_str, |_, this, val: #field_ty| {
+					this.#field_member = val;
+					return Ok(());
+				});
 			});
-		});
+			projected_fields.push(quote! {
+				fields.add_field_method_set(#field_name_str, move |_, projection, val: #field_ty| {
+					let mut val: Option<#field_ty> = Some(val);
+					return place(projection)(&mut |this: &mut Self| {
+						if let Some(val) = val.take() {
+							this.#field_member = val;
+						};
+					});
+				});
+			});
+		};
 	}
 
-	// Create the helper function `_to_mlua_fields`
+	if let Err(e) = errors.finish() {
+		return e.write_errors().into();
+	};
+
+	// Positional access needs `__index`/`__newindex`, which live on the methods side,
+	// so it claims them like an `#[implementation]` block would
+	let mut user_data_field_methods: Vec<proc_macro2::TokenStream> = Vec::new();
+	let mut export_markers: Vec<Ident> = Vec::new();
+	if let (false, Some(span)) = (index_getters.is_empty(), positional_span) {
+		export_markers.push(implementation::export_marker("__index", span));
+	};
+	if let (false, Some(span)) = (index_setters.is_empty(), positional_span) {
+		export_markers.push(implementation::export_marker("__newindex", span));
+	};
+	let export_marker_items = export_markers
+		.iter()
+		.map(implementation::export_marker_item);
+	if !index_getters.is_empty() {
+		user_data_field_methods.push(quote! {
+			methods.add_meta_function(mlua::MetaMethod::Index, |lua, (user_data, key): (mlua::AnyUserData, mlua::Value)| {
+				match key.as_integer() {
+					#(#index_getters)*
+					_ => {}
+				};
+				return Err(mlua::Error::runtime(format!("attempt to get an unknown field '{}'", key.to_string()?)));
+			});
+		});
+	};
+	if !index_setters.is_empty() {
+		user_data_field_methods.push(quote! {
+			methods.add_meta_method_mut(mlua::MetaMethod::NewIndex, |lua, this, (key, value): (mlua::Value, mlua::Value)| {
+				match key.as_integer() {
+					#(#index_setters)*
+					_ => {}
+				};
+				return Err(mlua::Error::runtime(format!("attempt to set an unknown field '{}'", key.to_string()?)));
+			});
+		});
+	};
+
+	structure::strip_field_attrs(&mut ast.fields);
+	let name: &Ident = &ast.ident;
+	let place_bounds: proc_macro2::TokenStream = projection::place_bounds(&quote! { Self });
+	let generics: syn::Generics = generics::with_bounds(&ast.generics, field_bounds);
+	let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+	// Create the helper functions `_to_mlua_fields`, `_to_mlua_field_methods` and `_to_mlua_projected_fields`
 	let helper_fn: proc_macro2::TokenStream = quote! {
-		impl #name {
+		impl #impl_generics #name #ty_generics #where_clause {
 			#[doc(hidden)]
 			pub fn _to_mlua_fields<F: mlua::UserDataFields<Self>>(fields: &mut F) -> () {
 				#(#user_data_fields)*
 			}
+
+			#[doc(hidden)]
+			pub fn _to_mlua_field_methods<M: mlua::UserDataMethods<Self>>(methods: &mut M) -> () {
+				#(#user_data_field_methods)*
+			}
+
+			#[doc(hidden)]
+			pub fn _to_mlua_projected_fields<R, P, F>(fields: &mut F, place: fn(&R) -> &P) -> ()
+			where
+				R: 'static,
+				P: #place_bounds,
+				F: mlua::UserDataFields<R>,
+			{
+				#(#projected_fields)*
+			}
+
+			#(#export_marker_items)*
 		}
 	};
 
@@ -117,6 +345,34 @@ pub fn structure(_attr: TokenStream, item: TokenStream) -> TokenStream {
 /// as static properties on the Lua UserData. This allows accessing
 /// them in Lua as `MyEnum.VariantA`.
 ///
+/// # Behavior
+/// * Unit variants are static fields, `MyEnum.VariantA`, each one value shared by the
+///   Lua state (so it works as a table key). Methods taking `&mut self` or `self`
+///   fail on it. Other values holding the same unit variant compare equal through `__eq`.
+///   `#[enumeration(unit_functions)]` registers them as zero-argument constructors,
+///   `MyEnum.VariantA()`, instead.
+/// * Other variants get a constructor: `MyEnum.VariantB(1)`, or
+///   `MyEnum.VariantC{ x = 1 }` for struct-like variants.
+/// * The `kind` field holds the name of the variant, and `is_variant_a()` (the
+///   snake-cased name) tells whether a value is that variant.
+/// * Tuple payloads are read by position (`value[1]`), struct-like payloads by name
+///   (`value.x`). Reading a payload the variant does not have raises an error.
+///   Positional payloads take `__index`, and `#[implementation]` fns cannot
+///   claim it (or an `is_<variant>` name) as well.
+/// * `value:match{ VariantA = function() ... end, VariantB = function(n) ... end }`
+///   calls the arm of the value's variant with its payload. Every variant needs an
+///   arm unless a `_` arm is given; missing and unknown arms raise an error.
+///
+/// # Plain representations
+/// `#[enumeration(repr = "string")]` and `#[enumeration(repr = "integer")]` make a
+/// fieldless enum convert to and from its variant name or discriminant instead of
+/// userdata. Only `IntoLua` and `FromLua` are generated, so such enums are not
+/// passed to `compile!`. Unknown names and values are rejected with the valid ones.
+///
+/// `#[enumeration(repr = "table")]` works for any enum, converting to and from a
+/// table tagged with the variant name: `{ tag = "Attacking", 20 }` for tuple
+/// variants, `{ tag = "Move", dx = 1, dy = 2 }` for struct-like ones.
+///
 /// # Example:
 /// ```ignore
 /// #[derive(Clone, Copy)] // Required for UserData methods
@@ -129,15 +385,49 @@ pub fn structure(_attr: TokenStream, item: TokenStream) -> TokenStream {
 ///
 /// This is intended to be used with `impl mlua::UserData`.
 #[proc_macro_attribute]
-pub fn enumeration(__attr: TokenStream, item: TokenStream) -> TokenStream {
+pub fn enumeration(attr: TokenStream, item: TokenStream) -> TokenStream {
 	let ast: syn::ItemEnum = parse_macro_input!(item as syn::ItemEnum);
+	let enumeration_args: enumeration::EnumerationArgs =
+		match enumeration::parse_enumeration_args(attr) {
+			Ok(enumeration_args) => enumeration_args,
+			Err(e) => return e.write_errors().into(),
+		};
 	let name: &Ident = &ast.ident;
 	// let name_str: String = name.to_string();
 
-	// Build registrations for unit variants (register as static constructors)
+	// Plain Lua values need no userdata helpers, only the conversions
+	if let Some(repr) = enumeration_args.repr {
+		let repr_tokens: proc_macro2::TokenStream = match enumeration::repr_impls(&ast, repr) {
+			Ok(repr_tokens) => repr_tokens,
+			Err(e) => return e.write_errors().into(),
+		};
+		let output: proc_macro2::TokenStream = quote! {
+			#ast
+			#repr_tokens
+		};
+
+		return output.into();
+	};
+
+	// Build registrations for the variants: values for unit variants, constructors for the others
 	let mut variant_registrations: Vec<proc_macro2::TokenStream> = Vec::new();
+	let mut unit_variant_fields: Vec<proc_macro2::TokenStream> = Vec::new();
+	let mut variant_bounds: Vec<syn::WherePredicate> = Vec::new();
 	for variant in &ast.variants {
+		// Payloads that depend on a type parameter only convert for some instantiations
+		for field in variant.fields.iter() {
+			let field_ty: &syn::Type = &field.ty;
+			if generics::mentions_type_param(field_ty, &ast.generics) {
+				variant_bounds.push(syn::parse_quote! {
+					#field_ty: mlua::IntoLua + mlua::FromLua + Clone
+				});
+			};
+		}
+
 		match &variant.fields {
+			Fields::Unit if !enumeration_args.unit_functions => {
+				unit_variant_fields.push(enumeration::unit_variant_field(&variant.ident));
+			}
 			Fields::Unit => {
 				let variant_name: &Ident = &variant.ident;
 				let variant_name_str: String = variant_name.to_string();
@@ -146,7 +436,7 @@ pub fn enumeration(__attr: TokenStream, item: TokenStream) -> TokenStream {
 				variant_registrations.push(quote! {
 					// e.g. methods.add_function("Idle", |_, (): ()| Ok(PlayerStatus::Idle));
 					methods.add_function(#variant_name_str, |_, (): ()| {
-						Ok(#name::#variant_name)
+						Ok(Self::#variant_name)
 					});
 				});
 			}
@@ -165,7 +455,7 @@ pub fn enumeration(__attr: TokenStream, item: TokenStream) -> TokenStream {
 
 				variant_registrations.push(quote! {
 					methods.add_function(#variant_name_str, |_, (#(#arg_idents),*): (#(#field_types),*)| {
-						Ok(#name::#variant_name(#(#arg_idents),*))
+						Ok(Self::#variant_name(#(#arg_idents),*))
 					});
 				});
 			}
@@ -183,8 +473,8 @@ pub fn enumeration(__attr: TokenStream, item: TokenStream) -> TokenStream {
 
 				variant_registrations.push(quote! {
 					methods.add_function(#variant_name_str, |_, tbl: mlua::Table| {
-						Ok(#name::#variant_name {
-							#(#names: tbl.get::<_, #types>(stringify!(#names))?),*
+						Ok(Self::#variant_name {
+							#(#names: tbl.get::<#types>(stringify!(#names))?),*
 						})
 					});
 				});
@@ -192,14 +482,39 @@ pub fn enumeration(__attr: TokenStream, item: TokenStream) -> TokenStream {
 		};
 	}
 
+	// `kind`, payload fields, `is_<variant>` predicates and positional payload access
+	let mut variant_field_registrations: Vec<proc_macro2::TokenStream> =
+		match enumeration::field_registrations(&ast) {
+			Ok(variant_field_registrations) => variant_field_registrations,
+			Err(e) => return e.write_errors().into(),
+		};
+	variant_field_registrations.extend(unit_variant_fields);
+	variant_registrations.extend(enumeration::method_registrations(&ast));
+	let export_marker_items = enumeration::export_markers(&ast)
+		.into_iter()
+		.map(|export_marker: Ident| implementation::export_marker_item(&export_marker));
+
 	// Create helper fn _to_mlua_variants, plus FromLua and IntoLua impls for lossless userdata round-trip.
 	// FromLua requires Clone so we can return owned values from borrowed userdata.
+	if ast.generics.type_params().next().is_some() {
+		variant_bounds.push(syn::parse_quote! { Self: mlua::IntoLua });
+	};
+	let generics: syn::Generics = generics::with_bounds(&ast.generics, variant_bounds);
+	let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
 	let helper_fn: proc_macro2::TokenStream = quote! {
-		impl #name {
+		impl #impl_generics #name #ty_generics #where_clause {
 			#[doc(hidden)]
 			pub fn _to_mlua_variants<M: mlua::UserDataMethods<Self>>(methods: &mut M) -> () {
 				#(#variant_registrations)*;
 			}
+
+			#[doc(hidden)]
+			pub fn _to_mlua_variant_fields<F: mlua::UserDataFields<Self>>(fields: &mut F) -> () {
+				#(#variant_field_registrations)*
+			}
+
+			#(#export_marker_items)*
 		}
 	};
 
@@ -230,6 +545,73 @@ pub fn enumeration(__attr: TokenStream, item: TokenStream) -> TokenStream {
 ///   immutable methods, accessible in Lua as `my_instance:my_method()`.
 /// * **Mutable Methods** (e.g., `fn my_mut_method(&mut self)`) are registered as
 ///   mutable methods, accessible in Lua as `my_instance:my_mut_method()`.
+/// * **Consuming Methods** (e.g., `fn into_corpse(self)`) take the value out of the
+///   userdata, which raises an error on any later use from Lua.
+/// * **Associated Consts** (e.g., `const MAX_HP: i32 = 100`) are registered as static
+///   fields, readable from the type in Lua as `MyType.MAX_HP`.
+/// * Arguments may use any pattern (`_: i32`, `Point { x, y }: Point`, ...).
+///   Tuple arguments take one Lua argument per element: `(x, y): (f32, f32)`
+///   is passed as `f(x, y)`.
+/// * Reference arguments are borrowed from the Lua value: `&str` and `&[u8]` from a
+///   string, `&[T]` from a sequence, and `&T`/`&mut T` from a `T` userdata. Borrowing
+///   a userdata that is already mutably borrowed (or `&mut` twice) raises an error.
+/// * `impl Fn(A) -> mlua::Result<R>` (or `FnMut`/`FnOnce`, `&dyn Fn`, `&mut dyn FnMut`)
+///   arguments take a Lua function. Reference arguments are cloned into Lua, and an
+///   error in the Lua function is handed back as `Err`. Callbacks returning anything
+///   but `mlua::Result<_>` are a compile error.
+/// * `#[lua(default = expr)]` on an argument lets Lua leave it out (or pass `nil`);
+///   `expr` is converted with `Into`, so `#[lua(default = "Squire")] title: String` works.
+///
+/// # Method attributes
+/// * `#[lua(meta = "__call")]` — the fn is registered as a metamethod instead of
+///   under its name. Any `mlua::MetaMethod` is accepted (`__len`, `__concat`,
+///   `__close`, `__index`, ...); unknown names are a compile error.
+/// * `#[lua(throws)]` — for a fn returning `Result<T, E>`, `Err(e)` is raised as a
+///   Lua error table `{ kind, message, code }` that scripts can `pcall` and inspect.
+///   `message` is `e`'s `Display`, `kind` the variant name from its `Debug`, and
+///   `code` is nil. `throws(kind = path, code = path)` computes them from `&E`
+///   instead. `#[implementation(throws)]` applies it to every fn returning a `Result`.
+/// * `#[lua(clone)]` — a fn taking `self` works on a copy, leaving the userdata intact.
+/// * `#[lua(table_args)]` — Lua passes one table keyed by parameter name,
+///   `Npc.new{ name = "orc", hp = 10 }`. Missing keys are `nil` (see `default`);
+///   unknown keys and conversion errors name the key and the fn.
+/// * `#[lua(name = "new")]` — the fn is registered under another name. Fns of a block
+///   sharing a name are overloads: the first one taking as many arguments as given,
+///   and whose arguments convert, is called (in declaration order), then the first
+///   one that accepts them by leaving out `default` arguments. Otherwise the
+///   error lists every signature. Overloads cannot be async or use `throws`.
+/// * `#[lua(iter)]` — the returned iterator (or `IntoIterator`) is handed to Lua as
+///   a generic-`for` iterator, `for item in bag:each() do`. An iterator borrowing
+///   `self` keeps the userdata borrowed until it runs out or the loop is left.
+///
+/// The attributes are removed from the impl block that is emitted again.
+///
+/// # Export policy
+/// `#[implementation(export = "pub")]` exports only `pub` and `pub(crate)` fns and
+/// consts, so private helpers stay out of Lua. `#[lua(export)]` and `#[lua(skip)]`
+/// override the policy per item. Without `export`, the crate-wide default from
+/// `export = "pub"` (or `"all"`) under `[package.metadata.mlua_magic_macros]` in
+/// `Cargo.toml` applies, which defaults to `"all"`. Trait impls export every item,
+/// and giving them `export` is an error.
+///
+/// # Multiple blocks
+/// A type may have several annotated `impl` blocks. Every block but one needs a
+/// tag, `#[implementation(tag = "combat")]`, which registers it under
+/// `_to_mlua_methods_combat`; `compile!` includes it through `tags(combat)`.
+/// Two blocks exporting the same Lua name fail to compile. A block nothing
+/// registers warns that `<tag>_missing_from_compile_tags` is never used, unless
+/// `#[allow(dead_code)]` is put on it.
+///
+/// # Trait impls
+/// Trait impl blocks (`impl Damageable for Npc`) are supported as well. Their
+/// methods are called as `<Npc as Damageable>::method`, and the block is tagged
+/// with the trait's name by default, so `compile!` includes it through
+/// `tags(Damageable)`. Impls of a generic trait (`impl From<i32>`) need a tag
+/// of their own, since the trait's name would be shared. An `impl Display` (or `impl Debug`) block becomes `__tostring`
+/// instead of exporting `fmt`, like `compile!(meta(display))`.
+///
+/// Argument and return types that do not convert to or from Lua (or `&T` arguments
+/// where `T` is no userdata, such as `&mut Formatter`) are reported on the type.
 ///
 /// # Usage
 /// Apply the macro directly to the `impl` block for the type:
@@ -262,97 +644,564 @@ pub fn enumeration(__attr: TokenStream, item: TokenStream) -> TokenStream {
 /// * `#[mlua_magic_macros::enumeration]` — for enum variants
 /// * `mlua_magic_macros::compile!` — final hookup to `mlua::UserData`
 #[proc_macro_attribute]
-pub fn implementation(_attr: TokenStream, item: TokenStream) -> TokenStream {
-	let ast: syn::ItemImpl = parse_macro_input!(item as syn::ItemImpl);
-	let name: &syn::Type = &ast.self_ty;
+pub fn implementation(attr: TokenStream, item: TokenStream) -> TokenStream {
+	let mut ast: syn::ItemImpl = parse_macro_input!(item as syn::ItemImpl);
+	let implementation_args: implementation::ImplementationArgs =
+		match implementation::parse_implementation_args(attr) {
+			Ok(implementation_args) => implementation_args,
+			Err(e) => return e.write_errors().into(),
+		};
+	let name: syn::Type = (*ast.self_ty).clone();
+	let trait_path: Option<syn::Path> = ast
+		.trait_
+		.as_ref()
+		.map(|(_, trait_path, _)| trait_path.clone());
 
 	let mut method_registrations: Vec<proc_macro2::TokenStream> = Vec::new();
+	let mut method_field_registrations: Vec<proc_macro2::TokenStream> = Vec::new();
+	let mut has_throwing_fns: bool = false;
+	let mut has_iter_fns: bool = false;
+	let mut export_markers: Vec<Ident> = Vec::new();
+	let mut method_bounds: Vec<syn::WherePredicate> = Vec::new();
+	// Trait items are as visible as the trait, so trait impls export everything
+	let export: export::Export = if let Some(trait_path) = &trait_path {
+		if implementation_args.export.is_some() {
+			return darling::Error::custom(
+				"trait impls always export every item; `export` only applies to inherent impls",
+			)
+			.with_span(trait_path)
+			.write_errors()
+			.into();
+		};
+		export::Export::All
+	} else {
+		match export::Export::resolve(implementation_args.export) {
+			Ok(export) => export,
+			Err(e) => return e.with_span(&ast.self_ty).write_errors().into(),
+		}
+	};
+	let mut errors: darling::error::Accumulator = darling::Error::accumulator();
+	// `From<i32>` and `From<String>` would share the tag `From`, so generic traits need their own
+	if let Some(trait_path) = &trait_path
+		&& implementation_args.tag.is_none()
+		&& let Some(segment) = trait_path.segments.last()
+		&& !segment.arguments.is_none()
+	{
+		errors.push(
+			darling::Error::custom(format!(
+				"impls of a generic trait need an explicit `tag`, since `{}` could name several of them",
+				segment.ident,
+			))
+			.with_span(&segment),
+		);
+	};
+	let overloaded_names: Vec<String> = implementation::overloaded_names(&ast, export);
+	let mut overloads: Vec<(String, Vec<implementation::Overload>)> = Vec::new();
+
+	// `impl Display` and `impl Debug` have nothing to call from Lua, so they become `__tostring`
+	let tostring_format: Option<&str> = trait_path
+		.as_ref()
+		.and_then(implementation::tostring_format);
+	if let (Some(tostring_format), Some(trait_path)) = (tostring_format, &trait_path) {
+		export_markers.push(implementation::export_marker(
+			"__tostring",
+			syn::spanned::Spanned::span(trait_path),
+		));
+		method_registrations.push(quote! {
+			methods.add_meta_method(mlua::MetaMethod::ToString, |_, this, (): ()| {
+				return Ok(format!(#tostring_format, this));
+			});
+		});
+	};
 
 	for item in &ast.items {
+		// Associated consts become static fields, readable on the proxy (`Player.MAX_HP`) too
+		if let syn::ImplItem::Const(const_item) = item {
+			let Some(const_args) = errors.handle(implementation::parse_const_args(const_item))
+			else {
+				continue;
+			};
+			if !export.includes(&const_item.vis, const_args.skip, const_args.export) {
+				continue;
+			};
+			let const_name: &Ident = &const_item.ident;
+			let const_name_str: String = const_name.to_string();
+			let const_ty: &syn::Type = &const_item.ty;
+			if generics::mentions_type_param(const_ty, &ast.generics) {
+				method_bounds.push(syn::parse_quote! { #const_ty: mlua::IntoLua });
+			};
+			let const_path: proc_macro2::TokenStream = match &trait_path {
+				Some(trait_path) => quote! { <Self as #trait_path>::#const_name },
+				None => quote! { Self::#const_name },
+			};
+			export_markers.push(implementation::export_marker(
+				&const_name_str,
+				const_name.span(),
+			));
+			method_field_registrations.push(quote! {
+				fields.add_field(#const_name_str, #const_path);
+			});
+			continue;
+		};
+
 		if let syn::ImplItem::Fn(fn_item) = item {
 			let fn_name: &Ident = &fn_item.sig.ident;
-			let fn_name_str: String = fn_name.to_string();
+			if tostring_format.is_some() && fn_name == "fmt" {
+				continue;
+			};
+			let Some(method_args) = errors.handle(implementation::parse_method_args(fn_item))
+			else {
+				continue;
+			};
+			if !export.includes(&fn_item.vis, method_args.skip, method_args.export) {
+				continue;
+			};
+
+			// Lua cannot pick a type for a generic method, only for the impl block
+			if fn_item.sig.generics.type_params().next().is_some()
+				|| fn_item.sig.generics.const_params().next().is_some()
+			{
+				errors.push(
+					darling::Error::custom("generic methods cannot be exported to Lua")
+						.with_span(&fn_item.sig.generics),
+				);
+				continue;
+			};
+
+			// `throws` on the fn, or on the block for every fn returning a `Result`
+			let result_types = throws::result_types(&fn_item.sig.output);
+			let throws_args: Option<throws::ThrowsArgs> =
+				match (&method_args.throws, &implementation_args.throws) {
+					(Some(_), _) if result_types.is_none() => {
+						errors.push(
+							darling::Error::custom("`throws` needs a fn returning a `Result`")
+								.with_span(&fn_item.sig.ident),
+						);
+						continue;
+					}
+					(Some(throws), _) => Some(throws.clone().unwrap_or_default()),
+					(None, Some(throws))
+						if result_types.is_some() && method_args.meta.is_none() =>
+					{
+						Some(throws.clone().unwrap_or_default())
+					}
+					_ => None,
+				};
+
+			// Metamethods are registered under their `mlua::MetaMethod`, everything else under its name
+			let meta_method: Option<Ident> = method_args
+				.meta
+				.as_deref()
+				.and_then(|meta: &String| implementation::meta_method(meta));
+			let fn_name_str: String = implementation::lua_name(fn_item, &method_args);
+
+			// Fns sharing a name are registered together, once the whole block is read
+			let overloaded: bool = overloaded_names.contains(&fn_name_str);
+			if overloaded && (throws_args.is_some() || fn_item.sig.asyncness.is_some()) {
+				errors.push(
+					darling::Error::custom("overloaded fns cannot be async or use `throws`")
+						.with_span(&fn_item.sig.ident),
+				);
+				continue;
+			};
+			let export_marker: Ident = implementation::export_marker(
+				&fn_name_str,
+				implementation::lua_name_span(fn_item, &method_args),
+			);
+			if !export_markers.contains(&export_marker) {
+				export_markers.push(export_marker);
+			};
 
-			// Extract argument names and types, skipping the `self` receiver
-			let (arg_names, arg_tys): (Vec<_>, Vec<_>) = fn_item
+			let key: proc_macro2::TokenStream = match &meta_method {
+				Some(meta_method) => quote! { mlua::MetaMethod::#meta_method },
+				None => quote! { #fn_name_str },
+			};
+			let adder = |kind: &str| -> Ident {
+				let is_async: &str = if fn_item.sig.asyncness.is_some() {
+					"async_"
+				} else {
+					""
+				};
+				let is_meta: &str = if meta_method.is_some() { "meta_" } else { "" };
+				return format_ident!("add_{}{}{}", is_async, is_meta, kind);
+			};
+
+			// Bind every typed argument to a synthetic name, whatever its pattern
+			// (`(x, y): (f32, f32)`, `_: i32`, ...); the fn destructures it itself
+			let mut lua_params: Vec<(proc_macro2::TokenStream, syn::Type)> = Vec::new();
+			let mut call_args: Vec<proc_macro2::TokenStream> = Vec::new();
+			let typed_args = fn_item
 				.sig
 				.inputs
 				.iter()
-				.filter_map(|arg| {
-					if let syn::FnArg::Typed(pat_type) = arg {
-						if let Pat::Ident(pat_ident) = &*pat_type.pat {
-							Some((&pat_ident.ident, &pat_type.ty))
-						} else {
-							None
-						}
-					} else {
-						None
-					}
-				})
-				.unzip();
+				.filter_map(|arg: &syn::FnArg| match arg {
+					syn::FnArg::Typed(pat_type) => Some(pat_type),
+					syn::FnArg::Receiver(_) => None,
+				});
+			let mut table_keys: Vec<String> = Vec::new();
+			let mut defaulted: usize = 0;
+			for (index, pat_type) in typed_args.enumerate() {
+				if implementation::has_default(pat_type) {
+					defaulted += 1;
+				};
+				let name: Ident = format_ident!("arg{}", index);
+				if let Some(call_arg) =
+					errors.handle(implementation::bind_param(name, pat_type, &mut lua_params))
+				{
+					call_args.push(call_arg);
+				};
+				if method_args.table_args
+					&& let Some(table_key) = errors.handle(implementation::table_key(pat_type))
+				{
+					table_keys.push(table_key);
+				};
+			}
+			if method_args.table_args && table_keys.len() != lua_params.len() {
+				continue;
+			};
+			let (arg_pats, arg_tys): (Vec<proc_macro2::TokenStream>, Vec<syn::Type>) =
+				lua_params.into_iter().unzip();
+
+			// Argument and return types that depend on the impl's type parameters need conversions
+			let is_generic: bool = ast.generics.type_params().next().is_some();
+			let depends_on_generics = |ty: &syn::Type| -> bool {
+				return generics::mentions_type_param(ty, &ast.generics)
+					|| (is_generic && generics::mentions_self(ty));
+			};
+			for arg_ty in &arg_tys {
+				if generics::mentions_type_param(arg_ty, &ast.generics) {
+					method_bounds.push(syn::parse_quote! { #arg_ty: mlua::FromLua });
+				};
+			}
+			match (&throws_args, result_types, &fn_item.sig.output) {
+				(Some(_), Some((value_ty, error_ty)), _) => {
+					if depends_on_generics(value_ty) {
+						method_bounds.push(syn::parse_quote! { #value_ty: mlua::IntoLuaMulti });
+					};
+					if let Some(error_ty) = error_ty
+						&& depends_on_generics(error_ty)
+					{
+						method_bounds.push(syn::parse_quote! {
+							#error_ty: ::std::fmt::Display + ::std::fmt::Debug
+						});
+					};
+				}
+				(_, _, syn::ReturnType::Type(_, output_ty))
+					if method_args.iter && depends_on_generics(output_ty) =>
+				{
+					method_bounds.push(iter::item_bound(output_ty));
+				}
+				(_, _, syn::ReturnType::Type(_, output_ty)) if depends_on_generics(output_ty) => {
+					method_bounds.push(syn::parse_quote! { #output_ty: mlua::IntoLuaMulti });
+				}
+				_ => {}
+			};
+			method_registrations.extend(implementation::conversion_checks(
+				fn_item,
+				throws_args
+					.as_ref()
+					.and(result_types.map(|(value_ty, _)| value_ty)),
+				method_args.iter,
+				depends_on_generics,
+			));
 
 			// Check if the function is async
 			let is_async = fn_item.sig.asyncness.is_some();
 
-			// Check for `&self`, `&mut self`, or static
-			if let Some(receiver) = &fn_item.sig.receiver() {
-				if receiver.mutability.is_some() {
-					// Here, `this` is `&mut self`
-					if is_async {
-						method_registrations.push(quote! {
-                            methods.add_async_method_mut(#fn_name_str, |_, mut this, (#(#arg_names,)*): (#(#arg_tys,)*)| async move {
-								return Ok(this.#fn_name(#(#arg_names,)*).await);
-                            });
-                        });
+			let fn_path: proc_macro2::TokenStream = match &trait_path {
+				Some(trait_path) => quote! { <Self as #trait_path>::#fn_name },
+				None => quote! { Self::#fn_name },
+			};
+
+			// Trait methods are called fully qualified, so the trait need not be in scope
+			let call = |this: proc_macro2::TokenStream| -> proc_macro2::TokenStream {
+				return match &trait_path {
+					Some(trait_path) => {
+						quote! { <Self as #trait_path>::#fn_name(#this, #(#call_args,)*) }
+					}
+					None => quote! { this.#fn_name(#(#call_args,)*) },
+				};
+			};
+
+			// Lua arguments beyond the receiver, and a statement run before the call
+			let mut param_pats: Vec<proc_macro2::TokenStream> = arg_pats;
+			let mut param_tys: Vec<proc_macro2::TokenStream> =
+				arg_tys.iter().map(|arg_ty| quote! { #arg_ty }).collect();
+			let mut prelude: Vec<proc_macro2::TokenStream> = Vec::new();
+
+			// `table_args` fns take one table, unpacked into the parameters before the call
+			if method_args.table_args {
+				prelude.push(implementation::unpack_table_args(
+					&fn_name_str,
+					&table_keys,
+					&param_pats
+						.iter()
+						.cloned()
+						.zip(arg_tys.iter().cloned())
+						.collect::<Vec<_>>(),
+				));
+				param_pats = vec![quote! { args_table }];
+				param_tys = vec![quote! { mlua::Table }];
+			};
+
+			has_iter_fns |= method_args.iter;
+
+			// `iter` fns whose result borrows `self` keep the userdata borrowed while iterating
+			let iter_borrows: bool = method_args.iter
+				&& fn_item
+					.sig
+					.receiver()
+					.is_some_and(|receiver| receiver.reference.is_some())
+				&& iter::borrows(&fn_item.sig.output);
+
+			// Check for `&self`, `&mut self`, `self`, or static
+			let (adder, this_param, invoke): (
+				Ident,
+				proc_macro2::TokenStream,
+				proc_macro2::TokenStream,
+			) = match fn_item.sig.receiver() {
+				Some(receiver)
+					if receiver.colon_token.is_some()
+						&& !matches!(&*receiver.ty, syn::Type::Reference(_))
+						&& !matches!(&*receiver.ty, syn::Type::Path(path) if path.path.is_ident("Self")) =>
+				{
+					errors.push(
+						darling::Error::custom(
+							"only `self`, `&self` and `&mut self` receivers can be exported to Lua",
+						)
+						.with_span(receiver),
+					);
+					continue;
+				}
+				// Here, `this` is `self`: a copy with `#[lua(clone)]`, otherwise taken out of the userdata
+				Some(receiver) if receiver.reference.is_none() => {
+					if method_args.clone {
+						if is_generic {
+							method_bounds.push(syn::parse_quote! { Self: mlua::FromLua });
+						};
+						param_pats.insert(0, quote! { this });
+						param_tys.insert(0, quote! { Self });
 					} else {
-						method_registrations.push(quote! {
-							methods.add_method_mut(#fn_name_str, |_, this, (#(#arg_names,)*): (#(#arg_tys,)*)| {
-								return Ok(this.#fn_name(#(#arg_names,)*));
-							});
+						param_pats.insert(0, quote! { user_data });
+						param_tys.insert(0, quote! { mlua::AnyUserData });
+						prelude.push(quote! {
+							// A projected field only lends a copy, which it writes back afterwards
+							if user_data.named_user_value::<Option<bool>>("__magic_projected")?.is_some() {
+								return Err(mlua::Error::runtime(concat!(
+									"`", #fn_name_str, "` takes the value, so it cannot be called through a projected field",
+								)));
+							};
+							let this: Self = user_data.take::<Self>()?;
 						});
+					};
+					(adder("function"), quote! {}, call(quote! { this }))
+				}
+				// Here, `this` is `&self` or `&mut self`, borrowed for as long as the iterator lives
+				Some(receiver) if iter_borrows => {
+					param_pats.insert(0, quote! { user_data });
+					param_tys.insert(0, quote! { mlua::AnyUserData });
+					prelude.push(iter::guard(receiver.mutability.is_some()));
+					(adder("function"), quote! {}, call(quote! { this }))
+				}
+				// Here, `this` is a borrow of the userdata, converted along with the other arguments
+				Some(receiver) if overloaded || throws_args.is_some() => {
+					let invoke: proc_macro2::TokenStream = if receiver.mutability.is_some() {
+						param_pats.insert(0, quote! { mut this });
+						param_tys.insert(0, quote! { mlua::UserDataRefMut<Self> });
+						call(quote! { &mut *this })
+					} else {
+						param_pats.insert(0, quote! { this });
+						param_tys.insert(0, quote! { mlua::UserDataRef<Self> });
+						call(quote! { &*this })
+					};
+					(adder("function"), quote! {}, invoke)
+				}
+				// Here, `this` is `&mut self`
+				Some(receiver) if receiver.mutability.is_some() => {
+					if is_async {
+						(
+							adder("method_mut"),
+							quote! { mut this, },
+							call(quote! { &mut this }),
+						)
+					} else {
+						(adder("method_mut"), quote! { this, }, call(quote! { this }))
 					}
-				} else {
-					// Here, `this` is `&self`
+				}
+				// Here, `this` is `&self`
+				Some(_) => {
 					if is_async {
-						method_registrations.push(quote! {
-                            methods.add_async_method(#fn_name_str, |_, this, (#(#arg_names,)*): (#(#arg_tys,)*)| async move {
-                        		return Ok(this.#fn_name(#(#arg_names,)*).await);
-                            });
-                        });
+						(adder("method"), quote! { this, }, call(quote! { &this }))
 					} else {
-						method_registrations.push(quote! {
-							methods.add_method(#fn_name_str, |_, this, (#(#arg_names,)*): (#(#arg_tys,)*)| {
-								return Ok(this.#fn_name(#(#arg_names,)*));
-							});
-						});
+						(adder("method"), quote! { this, }, call(quote! { this }))
 					}
+				}
+				// This is a static function (like `new`)
+				None => (
+					adder("function"),
+					quote! {},
+					quote! { #fn_path(#(#call_args,)*) },
+				),
+			};
+			if method_args.clone
+				&& !matches!(fn_item.sig.receiver(), Some(receiver) if receiver.reference.is_none())
+			{
+				errors.push(
+					darling::Error::custom("`clone` only applies to fns taking `self`")
+						.with_span(&fn_item.sig.ident),
+				);
+				continue;
+			};
+			let invoke: proc_macro2::TokenStream = if is_async {
+				quote! { #invoke.await }
+			} else {
+				invoke
+			};
+
+			if overloaded {
+				let body: proc_macro2::TokenStream = if method_args.iter {
+					iter::body(&invoke, iter_borrows)
+				} else {
+					quote! { return mlua::IntoLuaMulti::into_lua_multi(#invoke, lua); }
+				};
+				// A `table_args` fn takes its defaulted arguments inside the table
+				let required: usize = if method_args.table_args {
+					param_tys.len()
+				} else {
+					param_tys.len() - defaulted
+				};
+				let overload: implementation::Overload = implementation::Overload {
+					pats: param_pats,
+					tys: param_tys,
+					required: required,
+					body: quote! { { #(#prelude)* #body } },
+					signature: implementation::overload_signature(&fn_name_str, fn_item),
+				};
+				match overloads
+					.iter_mut()
+					.find(|(lua_name, _)| *lua_name == fn_name_str)
+				{
+					Some((_, group)) => group.push(overload),
+					None => overloads.push((fn_name_str, vec![overload])),
+				};
+				continue;
+			};
+
+			let (lua_param, body): (proc_macro2::TokenStream, proc_macro2::TokenStream) =
+				match &throws_args {
+					_ if method_args.iter => (quote! { lua }, iter::body(&invoke, iter_borrows)),
+					Some(throws_args) => (quote! { lua }, throws::raw_body(&invoke, throws_args)),
+					None => (quote! { _ }, quote! { return Ok(#invoke); }),
 				};
+			let body: proc_macro2::TokenStream = if is_async {
+				quote! { async move { #(#prelude)* #body } }
 			} else {
-				// This is a static function (like `new`)
-				if is_async {
-					method_registrations.push(quote! {
-						methods.add_async_function(#fn_name_str, |_, (#(#arg_names,)*): (#(#arg_tys,)*)| async {
-							return Ok(#name::#fn_name(#(#arg_names,)*).await);
-						});
-					});
+				quote! { { #(#prelude)* #body } }
+			};
+
+			// A throwing fn is exposed through a field getter, handing out a wrapper that
+			// raises the error table; the fn itself is only reachable through the wrapper
+			if throws_args.is_some() {
+				has_throwing_fns = true;
+				let creator: Ident = if is_async {
+					format_ident!("create_async_function")
 				} else {
-					method_registrations.push(quote! {
-						methods.add_function(#fn_name_str, |_, (#(#arg_names,)*): (#(#arg_tys,)*)| {
-							return Ok(#name::#fn_name(#(#arg_names,)*));
-						});
+					format_ident!("create_function")
+				};
+				method_field_registrations.push(quote! {
+					fields.add_field_function_get(#fn_name_str, |lua, _: mlua::AnyUserData| {
+						let key: String = format!("__magic_throws_{}::{}", ::std::any::type_name::<Self>(), #fn_name_str);
+						if let Some(function) = lua.named_registry_value::<Option<mlua::Function>>(&key)? {
+							return Ok(function);
+						};
+
+						let raw: mlua::Function = lua.#creator(|#lua_param, (#(#param_pats,)*): (#(#param_tys,)*)| #body)?;
+						let function: mlua::Function = throwing(lua, raw)?;
+						lua.set_named_registry_value(&key, &function)?;
+						return Ok(function);
 					});
-				}
+				});
+				continue;
 			};
+
+			method_registrations.push(quote! {
+				methods.#adder(#key, |#lua_param, #this_param (#(#param_pats,)*): (#(#param_tys,)*)| #body);
+			});
 		};
 	}
 
-	// Create the helper function `_to_mlua_methods`
+	for (lua_name, group) in &overloads {
+		method_registrations.push(implementation::overload_dispatch(lua_name, group));
+	}
+
+	if let Err(e) = errors.finish() {
+		return e.write_errors().into();
+	};
+	implementation::strip_method_attrs(&mut ast);
+
+	let generics: syn::Generics = generics::with_bounds(&ast.generics, method_bounds);
+	let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+	// Trait impls are tagged with the trait's name unless told otherwise
+	let tag: Option<&Ident> = implementation_args.tag.as_ref().or_else(|| {
+		return trait_path
+			.as_ref()
+			.and_then(|trait_path| trait_path.segments.last())
+			.map(|segment| &segment.ident);
+	});
+	let methods_helper: Ident = implementation::methods_helper(tag);
+	let method_fields_helper: Ident = implementation::method_fields_helper(tag);
+	let registration_check: Ident = implementation::registration_check(tag, ast.impl_token.span);
+	let registration_check_item: proc_macro2::TokenStream = quote_spanned! { registration_check.span() =>
+		#[doc(hidden)]
+		#[allow(non_upper_case_globals)]
+		const #registration_check: () = ();
+	};
+	let export_marker_items = export_markers
+		.iter()
+		.map(implementation::export_marker_item);
+	// `#[allow(...)]` on the block also covers its helpers
+	let lint_attrs: Vec<&syn::Attribute> = ast
+		.attrs
+		.iter()
+		.filter(|attr: &&syn::Attribute| attr.path().is_ident("allow"))
+		.collect();
+
+	let support_fns: proc_macro2::TokenStream = if has_throwing_fns {
+		throws::support_fns()
+	} else {
+		quote! { /* Do nothing */ }
+	};
+	let iter_support_fn: proc_macro2::TokenStream = if has_iter_fns {
+		iter::support_fn()
+	} else {
+		quote! { /* Do nothing */ }
+	};
+
+	// Create the helper functions `_to_mlua_methods` and `_to_mlua_method_fields`
+	// (or `..._<tag>`). They always live in an inherent impl, even for a trait impl block.
 	let helper_fn: proc_macro2::TokenStream = quote! {
-		impl #name {
+		#(#lint_attrs)*
+		impl #impl_generics #name #where_clause {
 			#[doc(hidden)]
-			pub fn _to_mlua_methods<M: mlua::UserDataMethods<Self>>(methods: &mut M) -> () {
+			#[allow(non_snake_case)]
+			pub(crate) fn #methods_helper<M: mlua::UserDataMethods<Self>>(methods: &mut M) -> () {
+				let _: () = Self::#registration_check;
+				#support_fns
+				#iter_support_fn
 				#(#method_registrations)*
 			}
+
+			#[doc(hidden)]
+			#[allow(non_snake_case)]
+			pub(crate) fn #method_fields_helper<F: mlua::UserDataFields<Self>>(fields: &mut F) -> () {
+				#support_fns
+				#(#method_field_registrations)*
+			}
+
+			#registration_check_item
+
+			#(#export_marker_items)*
 		}
 	};
 
@@ -369,6 +1218,52 @@ pub fn implementation(_attr: TokenStream, item: TokenStream) -> TokenStream {
 	return output.into();
 }
 
+/// Exports the `static` and `const` items of a module as static fields of a type.
+///
+/// # Behavior
+/// * Generates `_to_mlua_statics` inside the module, which `compile!` calls for
+///   every module listed in `statics(...)`.
+/// * Each item becomes a field named after it, readable on the proxy created by
+///   `load!` and on every instance. Values are cloned when the type is registered.
+/// * `static mut` items are rejected.
+///
+/// # Usage
+/// ```ignore
+/// #[mlua_magic::statics]
+/// mod player_config {
+///     pub static VERSION: &str = "1.2";
+///     pub const START_HP: i32 = 100;
+/// }
+///
+/// mlua_magic::compile!(type_path = Player, fields = true, statics(player_config));
+/// // Lua: print(Player.VERSION, Player.START_HP)
+/// ```
+#[proc_macro_attribute]
+pub fn statics(__attr: TokenStream, item: TokenStream) -> TokenStream {
+	let mut ast: syn::ItemMod = parse_macro_input!(item as syn::ItemMod);
+
+	let registrations: Vec<proc_macro2::TokenStream> = match statics::registrations(&ast) {
+		Ok(registrations) => registrations,
+		Err(e) => return e.write_errors().into(),
+	};
+
+	// Add `_to_mlua_statics` to the module
+	if let Some((_, items)) = &mut ast.content {
+		items.push(syn::parse_quote! {
+			#[doc(hidden)]
+			pub fn _to_mlua_statics<T, F: mlua::UserDataFields<T>>(fields: &mut F) -> () {
+				#(#registrations)*
+			}
+		});
+	};
+
+	let output: proc_macro2::TokenStream = quote! {
+		#ast
+	};
+
+	return output.into();
+}
+
 // # Bottom of file
 // TODO: Move out of lib.rs when possible
 
@@ -391,6 +1286,12 @@ pub fn implementation(_attr: TokenStream, item: TokenStream) -> TokenStream {
 ///
 /// // Generates `impl mlua::UserData for Player`
 /// mlua_magic::compile!(type_path = Player, fields = true, methods true);
+///
+/// // Tagged `#[implementation(tag = "combat")]` blocks are listed by tag
+/// mlua_magic::compile!(type_path = Player, fields = true, methods = true, tags(combat));
+///
+/// // The items of `#[statics]` modules become static fields
+/// mlua_magic::compile!(type_path = Player, fields = true, statics(player_config));
 /// ```
 ///
 /// # Example (for an enum):
@@ -406,11 +1307,45 @@ pub fn implementation(_attr: TokenStream, item: TokenStream) -> TokenStream {
 /// // Generates `impl mlua::UserData for Status` and `impl mlua::IntoLua for Status`
 /// mlua_magic::compile!(type_path = Status, variants = true, methods = true);
 /// ```
+///
+/// # Example (with metamethods):
+/// ```ignore
+/// // `__tostring` from `Display` (or `debug` for `Debug`), `__eq` from `PartialEq`,
+/// // `__lt` and `__le` from `PartialOrd`
+/// mlua_magic::compile!(type_path = Status, variants = true, meta(display, eq, ord));
+///
+/// // Operators from `std::ops`: `add`/`sub`/`mul`/`div`/`rem` take the right-hand
+/// // operand types (`Self` by default), `index` an accessor `fn(&Self, K) -> Option<V>`
+/// mlua_magic::compile!(type_path = Vec2, fields = true, ops(add, mul(Self, f32), neg, index = Vec2::get));
+/// ```
+///
+/// # Example (for a generic type):
+/// ```ignore
+/// // A single instantiation...
+/// mlua_magic::compile!(type_path = Grid<f32>, fields = true, methods = true);
+///
+/// // ...or every instantiation whose parameters convert to and from Lua.
+/// // `bounds` adds where-predicates the helpers need on top of that.
+/// mlua_magic::compile!(type_path = Pair<T>, generics = <T>, bounds = "T: Default", fields = true);
+/// ```
 #[proc_macro]
 pub fn compile(input: TokenStream) -> TokenStream {
-	let compile_args: compile::CompileArgs = parse_compile_args(input).unwrap();
+	let compile_args: compile::CompileArgs = match parse_compile_args(input) {
+		Ok(compile_args) => compile_args,
+		Err(e) => return e.to_compile_error().into(),
+	};
 	let type_path: TypePath = compile_args.type_path.clone().expect("Type is required.");
 
+	// A generic impl requires every type parameter to round-trip through Lua
+	let mut bounds: Vec<syn::WherePredicate> = generics::lua_value_bounds(&compile_args.generics);
+	bounds.extend(compile_args.bounds.iter().cloned());
+	if !compile_args.generics.params.is_empty() {
+		bounds.extend(compile_args.meta.bounds(&type_path));
+		bounds.extend(compile_args.ops.bounds(&type_path));
+	};
+	let generics: syn::Generics = generics::with_bounds(&compile_args.generics, bounds);
+	let (impl_generics, _, where_clause) = generics.split_for_impl();
+
 	// Conditionally generate the call to the helper function
 	let fields_call: proc_macro2::TokenStream = if compile_args.fields.unwrap_or(false) {
 		quote! {
@@ -420,6 +1355,14 @@ pub fn compile(input: TokenStream) -> TokenStream {
 		quote! { /* Do nothing */ }
 	};
 
+	let field_methods_call: proc_macro2::TokenStream = if compile_args.fields.unwrap_or(false) {
+		quote! {
+			Self::_to_mlua_field_methods(methods);
+		}
+	} else {
+		quote! { /* Do nothing */ }
+	};
+
 	let methods_call: proc_macro2::TokenStream = if compile_args.methods.unwrap_or(false) {
 		quote! {
 			Self::_to_mlua_methods(methods);
@@ -428,6 +1371,47 @@ pub fn compile(input: TokenStream) -> TokenStream {
 		quote! { /* Do nothing */ }
 	};
 
+	let method_fields_call: proc_macro2::TokenStream = if compile_args.methods.unwrap_or(false) {
+		quote! {
+			Self::_to_mlua_method_fields(fields);
+		}
+	} else {
+		quote! { /* Do nothing */ }
+	};
+
+	// Modules marked `#[statics]`
+	let statics_calls: Vec<proc_macro2::TokenStream> = compile_args
+		.statics
+		.iter()
+		.map(|module: &syn::Path| {
+			return quote! {
+				#module::_to_mlua_statics(fields);
+			};
+		})
+		.collect();
+
+	// Tagged `#[implementation(tag = "...")]` blocks
+	let tagged_methods_calls: Vec<proc_macro2::TokenStream> = compile_args
+		.tags
+		.iter()
+		.map(|tag: &syn::Path| {
+			let methods_helper: Ident = implementation::methods_helper(tag.get_ident());
+			return quote! {
+				Self::#methods_helper(methods);
+			};
+		})
+		.collect();
+	let tagged_method_fields_calls: Vec<proc_macro2::TokenStream> = compile_args
+		.tags
+		.iter()
+		.map(|tag: &syn::Path| {
+			let method_fields_helper: Ident = implementation::method_fields_helper(tag.get_ident());
+			return quote! {
+				Self::#method_fields_helper(fields);
+			};
+		})
+		.collect();
+
 	let variants_call: proc_macro2::TokenStream = if compile_args.variants.unwrap_or(false) {
 		quote! {
 			Self::_to_mlua_variants(methods);
@@ -436,25 +1420,82 @@ pub fn compile(input: TokenStream) -> TokenStream {
 		quote! { /* Do nothing */ }
 	};
 
+	let variant_fields_call: proc_macro2::TokenStream = if compile_args.variants.unwrap_or(false) {
+		quote! {
+			Self::_to_mlua_variant_fields(fields);
+		}
+	} else {
+		quote! { /* Do nothing */ }
+	};
+
+	let meta_registrations: proc_macro2::TokenStream = compile_args
+		.meta
+		.registrations(&type_path, &quote! { this });
+	let ops_registrations: proc_macro2::TokenStream =
+		compile_args.ops.registrations(&type_path, true);
+	let ops_marker_items = compile_args
+		.ops
+		.export_markers()
+		.into_iter()
+		.map(|export_marker: Ident| implementation::export_marker_item(&export_marker));
+
+	// Projections get the same metamethods, working on a snapshot of the value
+	let projected_meta_registrations: proc_macro2::TokenStream = {
+		let meta: proc_macro2::TokenStream = compile_args
+			.meta
+			.registrations(&type_path, &quote! { Self::snapshot(&this.0)? });
+		let ops: proc_macro2::TokenStream = compile_args.ops.registrations(&type_path, false);
+		quote! { #meta #ops }
+	};
+	let projection_tokens: proc_macro2::TokenStream = projection::projection_tokens(
+		&type_path,
+		&generics,
+		compile_args.fields.unwrap_or(false),
+		&projected_meta_registrations,
+	);
+
+	// The export default in `Cargo.toml` is read by the attribute macros
+	let manifest_tracking: proc_macro2::TokenStream = export::Export::track_manifest();
+
 	// Assemble the final `impl mlua::UserData` block
 	let output: proc_macro2::TokenStream = quote! {
-		impl mlua::UserData for #type_path {
+		impl #impl_generics mlua::UserData for #type_path #where_clause {
 			fn add_fields<'lua, F: mlua::UserDataFields<Self>>(fields: &mut F) -> () {
 				#fields_call
+				#variant_fields_call
+				#method_fields_call
+				#(#tagged_method_fields_calls)*
+				#(#statics_calls)*
 			}
 
 			fn add_methods<'lua, M: mlua::UserDataMethods<Self>>(methods: &mut M) -> () {
+				#field_methods_call
 				#methods_call
+				#(#tagged_methods_calls)*
 				#variants_call
+				#meta_registrations
+				#ops_registrations
 			}
 		}
-		impl mlua::FromLua for #type_path {
+		impl #impl_generics #type_path #where_clause {
+			#(#ops_marker_items)*
+		}
+		impl #impl_generics mlua::FromLua for #type_path #where_clause {
 			fn from_lua(value: mlua::Value, lua: &mlua::Lua) -> mlua::Result<Self> {
 				let output: mlua::Result<Self> = match value {
 					mlua::Value::UserData(user_data) => {
-						return match user_data.borrow::<Self>() {
-							Ok(b) => Ok((*b).clone()),
-							Err(_) => Err(mlua::Error::FromLuaConversionError {
+						if let Ok(b) = user_data.borrow::<Self>() {
+							return Ok((*b).clone());
+						};
+
+						// Projections (`#[lua(project)]` fields) hand out a copy of the value they point at
+						let snapshot: Option<mlua::Function> = user_data
+							.metatable()
+							.and_then(|metatable| metatable.get::<Option<mlua::Function>>("__magic_snapshot"))
+							.unwrap_or(None);
+						return match snapshot.map(|snapshot| snapshot.call::<mlua::UserDataRef<Self>>(&user_data)) {
+							Some(Ok(b)) => Ok((*b).clone()),
+							_ => Err(mlua::Error::FromLuaConversionError {
 								from: "UserData",
 								to: stringify!(#type_path).to_string(),
 								message: Some("userdata is not this exact Rust type".into()),
@@ -471,6 +1512,10 @@ pub fn compile(input: TokenStream) -> TokenStream {
 				return output;
 			}
 		}
+		const _: () = {
+			#projection_tokens
+		};
+		#manifest_tracking
 		/*impl #type_path {
 			#[doc(hidden)]
 			pub fn _to_mlua_skeleton(lua: &mlua::Lua) -> Result<mlua::AnyUserData, mlua::Error> { // Spooky scary skeletons
@@ -524,13 +1569,19 @@ pub fn compile(input: TokenStream) -> TokenStream {
 ///         print(PlayerStatus) -- "userdata: PlayerStatus"
 ///
 ///         local p = Player.new("Hero")
-///         p.status = PlayerStatus.Walking()
+///         p.status = PlayerStatus.Walking
 ///     "#).exec()?;
 ///
 ///     Ok(())
 /// }
 /// ```
 ///
+/// Generic instantiations are registered under an explicit name:
+///
+/// ```ignore
+/// mlua_magic_macros::load!(lua, Grid<f32> as Grid);
+/// ```
+///
 /// # Prerequisites
 /// All types passed to `load!` must implement `mlua::UserData`. This is
 /// typically handled by using the `mlua_magic_macros::compile!` macro.
@@ -539,6 +1590,7 @@ pub fn load(input: TokenStream) -> TokenStream {
 	let load::LoadInput {
 		lua_expr,
 		type_paths,
+		names,
 	} = parse_macro_input!(input as load::LoadInput);
 
 	let output: proc_macro2::TokenStream = quote! {{
@@ -546,8 +1598,8 @@ pub fn load(input: TokenStream) -> TokenStream {
 		let globals: mlua::Table = lua.globals();
 
 		#(
-			// Register type globally under its Rust name
-			globals.set(stringify!(#type_paths), lua.create_proxy::<#type_paths>()?)?;
+			// Register type globally under its Rust name (or the `as` name)
+			globals.set(#names, lua.create_proxy::<#type_paths>()?)?;
 		)*
 	}};
 
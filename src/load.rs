@@ -1,21 +1,26 @@
 use ::syn::{
-	Token, Type, TypePath,
+	GenericArgument, PathArguments, Token, Type, TypePath,
 	parse::{self, Parse, ParseStream},
 };
 
-use ::proc_macro2::Ident;
+use ::proc_macro2::{Ident, TokenStream};
+
+use ::quote::quote;
 
 /// Helper struct for parsing the `load!` macro input
 pub struct LoadInput {
 	pub lua_expr: Ident,
 	pub type_paths: Vec<TypePath>,
+	/// The global each type is registered under (`Type as Name`)
+	pub names: Vec<TokenStream>,
 }
 
-/// Custom parser for `lua, MyStruct, MyEnum, ...`
+/// Custom parser for `lua, MyStruct, MyEnum, Grid<f32> as GridF32, ...`
 impl Parse for LoadInput {
 	fn parse(input: ParseStream) -> parse::Result<Self> {
 		let lua_expr: Ident = input.parse()?;
 		let mut type_paths: Vec<TypePath> = Vec::new();
+		let mut names: Vec<TokenStream> = Vec::new();
 
 		// Continue parsing idents as long as there's a comma
 		while !input.is_empty() {
@@ -25,16 +30,46 @@ impl Parse for LoadInput {
 			}; // Allow trailing comma
 			// Get the type path
 
-			if let Type::Path(type_path) = input.parse()? {
-				type_paths.push(type_path);
-			} else {
+			let Type::Path(type_path) = input.parse()? else {
 				panic!("Expected a type path");
 			};
+
+			// Generic instantiations have no sensible global name of their own
+			let name: TokenStream = if input.peek(Token![as]) {
+				input.parse::<Token![as]>()?;
+				let name: Ident = input.parse()?;
+				quote! { stringify!(#name) }
+			} else if has_generic_args(&type_path) {
+				return Err(parse::Error::new_spanned(
+					&type_path,
+					"generic types need a global name, e.g. `Grid<f32> as GridF32`",
+				));
+			} else {
+				quote! { stringify!(#type_path) }
+			};
+
+			type_paths.push(type_path);
+			names.push(name);
 		}
 
 		return Ok(Self {
 			lua_expr: lua_expr,
 			type_paths: type_paths,
+			names: names,
 		});
 	}
 }
+
+fn has_generic_args(type_path: &TypePath) -> bool {
+	return type_path
+		.path
+		.segments
+		.iter()
+		.any(|segment| match &segment.arguments {
+			PathArguments::AngleBracketed(args) => args
+				.args
+				.iter()
+				.any(|arg: &GenericArgument| !matches!(arg, GenericArgument::Lifetime(_))),
+			_ => false,
+		});
+}
@@ -0,0 +1,105 @@
+use ::proc_macro2::TokenStream;
+
+use ::quote::quote;
+
+use ::syn::{TypePath, WherePredicate};
+
+use ::darling::FromMeta;
+
+/// Metamethods derived from std traits, given through `compile!(meta(...))`
+#[derive(Debug, Default, FromMeta)]
+pub struct MetaArgs {
+	/// `__tostring` through `Display`
+	#[darling(default)]
+	pub display: bool,
+	/// `__tostring` through `Debug`
+	#[darling(default)]
+	pub debug: bool,
+	/// `__eq` through `PartialEq`
+	#[darling(default)]
+	pub eq: bool,
+	/// `__lt` and `__le` through `PartialOrd`
+	#[darling(default)]
+	pub ord: bool,
+}
+
+impl MetaArgs {
+	pub fn validate(&self) -> darling::Result<()> {
+		if self.display && self.debug {
+			return Err(darling::Error::custom(
+				"`display` and `debug` both define `__tostring`, pick one",
+			));
+		};
+
+		return Ok(());
+	}
+
+	/// The trait bounds the metamethods need, for generic `compile!` impls
+	pub fn bounds(&self, type_path: &TypePath) -> Vec<WherePredicate> {
+		let mut bounds: Vec<WherePredicate> = Vec::new();
+		if self.display {
+			bounds.push(syn::parse_quote! { #type_path: ::std::fmt::Display });
+		};
+		if self.debug {
+			bounds.push(syn::parse_quote! { #type_path: ::std::fmt::Debug });
+		};
+		if self.eq {
+			bounds.push(syn::parse_quote! { #type_path: ::std::cmp::PartialEq });
+		};
+		if self.ord {
+			bounds.push(syn::parse_quote! { #type_path: ::std::cmp::PartialOrd });
+		};
+
+		return bounds;
+	}
+
+	/// `add_methods` registrations for `type_path`.
+	///
+	/// `snapshot` turns the `this` of a `add_meta_method` into something that
+	/// derefs to the value, so projections can reuse these registrations.
+	pub fn registrations(&self, type_path: &TypePath, snapshot: &TokenStream) -> TokenStream {
+		let mut registrations: Vec<TokenStream> = Vec::new();
+
+		let format: Option<TokenStream> = if self.display {
+			Some(quote! { "{}" })
+		} else if self.debug {
+			Some(quote! { "{:?}" })
+		} else {
+			None
+		};
+		if let Some(format) = format {
+			registrations.push(quote! {
+				methods.add_meta_method(mlua::MetaMethod::ToString, |_, this, ()| {
+					return Ok(format!(#format, #snapshot));
+				});
+			});
+		};
+
+		// Operands go through `FromLua`, so a projection compares like the value it points at
+		if self.eq {
+			registrations.push(quote! {
+				methods.add_meta_function(mlua::MetaMethod::Eq, |lua, (a, b): (mlua::Value, mlua::Value)| {
+					let a: mlua::Result<#type_path> = mlua::FromLua::from_lua(a, lua);
+					let b: mlua::Result<#type_path> = mlua::FromLua::from_lua(b, lua);
+
+					return Ok(match (a, b) {
+						(Ok(a), Ok(b)) => a == b,
+						_ => false,
+					});
+				});
+			});
+		};
+		if self.ord {
+			registrations.push(quote! {
+				methods.add_meta_function(mlua::MetaMethod::Lt, |_, (a, b): (#type_path, #type_path)| {
+					return Ok(a < b);
+				});
+				methods.add_meta_function(mlua::MetaMethod::Le, |_, (a, b): (#type_path, #type_path)| {
+					return Ok(a <= b);
+				});
+			});
+		};
+
+		return quote! { #(#registrations)* };
+	}
+}
@@ -0,0 +1,241 @@
+use ::proc_macro2::{Ident, TokenStream};
+
+use ::quote::{format_ident, quote};
+
+use ::syn::{Expr, Meta, Path, Type, TypePath, WherePredicate, spanned::Spanned};
+
+use ::darling::{FromMeta, ast::NestedMeta};
+
+use crate::implementation;
+
+/// Right-hand operand types of an operator: `add` (just `Self`), `mul = f32` or `mul(Self, f32)`
+#[derive(Debug, Clone)]
+pub struct Operands(pub Vec<Type>);
+
+impl FromMeta for Operands {
+	fn from_word() -> darling::Result<Self> {
+		return Ok(Self(vec![syn::parse_quote! { Self }]));
+	}
+
+	fn from_expr(expr: &Expr) -> darling::Result<Self> {
+		return match expr {
+			Expr::Path(path) => Ok(Self(vec![Type::Path(TypePath {
+				qself: path.qself.clone(),
+				path: path.path.clone(),
+			})])),
+			Expr::Lit(lit) => Self::from_value(&lit.lit),
+			_ => Err(darling::Error::unexpected_expr_type(expr)),
+		};
+	}
+
+	fn from_string(value: &str) -> darling::Result<Self> {
+		return Ok(Self(vec![Type::from_string(value)?]));
+	}
+
+	fn from_list(items: &[NestedMeta]) -> darling::Result<Self> {
+		return items
+			.iter()
+			.map(|item: &NestedMeta| match item {
+				NestedMeta::Meta(Meta::Path(path)) => Ok(Type::Path(TypePath {
+					qself: None,
+					path: path.clone(),
+				})),
+				NestedMeta::Lit(lit) => Type::from_value(lit),
+				_ => Err(darling::Error::unsupported_format("nested meta").with_span(item)),
+			})
+			.collect::<darling::Result<Vec<Type>>>()
+			.map(Self);
+	}
+}
+
+/// Operator metamethods from `std::ops` impls, given through `compile!(ops(...))`
+#[derive(Debug, Default, FromMeta)]
+pub struct OpsArgs {
+	#[darling(default)]
+	pub add: Option<Operands>,
+	#[darling(default)]
+	pub sub: Option<Operands>,
+	#[darling(default)]
+	pub mul: Option<Operands>,
+	#[darling(default)]
+	pub div: Option<Operands>,
+	#[darling(default)]
+	pub rem: Option<Operands>,
+	#[darling(default)]
+	pub neg: bool,
+	/// The accessor behind `__index`, a `fn(&Self, K) -> Option<V>` such as `index = Vec2::get`
+	#[darling(default)]
+	pub index: Option<Path>,
+}
+
+/// A binary operator: its `std::ops` trait and method, the Lua metamethod,
+/// and whether `2 * v` may be computed as `v * 2`
+struct BinaryOp<'a> {
+	operands: &'a Operands,
+	trait_name: Ident,
+	method: Ident,
+	meta_method: Ident,
+	commutative: bool,
+}
+
+impl OpsArgs {
+	fn binary_ops(&self) -> Vec<BinaryOp<'_>> {
+		let ops: [(&Option<Operands>, &str, &str, bool); 5] = [
+			(&self.add, "Add", "Add", true),
+			(&self.sub, "Sub", "Sub", false),
+			(&self.mul, "Mul", "Mul", true),
+			(&self.div, "Div", "Div", false),
+			(&self.rem, "Rem", "Mod", false),
+		];
+
+		return ops
+			.into_iter()
+			.filter_map(|(operands, trait_name, meta_method, commutative)| {
+				return operands.as_ref().map(|operands: &Operands| BinaryOp {
+					operands: operands,
+					trait_name: format_ident!("{}", trait_name),
+					method: format_ident!("{}", trait_name.to_lowercase()),
+					meta_method: format_ident!("{}", meta_method),
+					commutative: commutative,
+				});
+			})
+			.collect();
+	}
+
+	/// The trait bounds the metamethods need, for generic `compile!` impls
+	pub fn bounds(&self, type_path: &TypePath) -> Vec<WherePredicate> {
+		let mut bounds: Vec<WherePredicate> = Vec::new();
+
+		for op in self.binary_ops() {
+			let trait_name: &Ident = &op.trait_name;
+			for rhs in op
+				.operands
+				.0
+				.iter()
+				.map(|rhs: &Type| resolve(rhs, type_path))
+			{
+				bounds.push(syn::parse_quote! { #type_path: ::std::ops::#trait_name<#rhs> });
+				bounds.push(syn::parse_quote! {
+					<#type_path as ::std::ops::#trait_name<#rhs>>::Output: mlua::IntoLua
+				});
+				bounds.push(syn::parse_quote! { #rhs: mlua::FromLua });
+			}
+		}
+		if self.neg {
+			bounds.push(syn::parse_quote! { #type_path: ::std::ops::Neg });
+			bounds.push(syn::parse_quote! {
+				<#type_path as ::std::ops::Neg>::Output: mlua::IntoLua
+			});
+		};
+
+		return bounds;
+	}
+
+	/// `add_methods` registrations for `type_path`.
+	///
+	/// Operands go through `FromLua`, so projections can reuse these registrations
+	/// (all but `__index`, which projections already forward to the value).
+	pub fn registrations(&self, type_path: &TypePath, with_index: bool) -> TokenStream {
+		let mut registrations: Vec<TokenStream> = Vec::new();
+
+		for op in self.binary_ops() {
+			let BinaryOp {
+				trait_name,
+				method,
+				meta_method,
+				commutative,
+				..
+			} = &op;
+			let meta_method_str: String = format!("__{}", meta_method.to_string().to_lowercase());
+
+			// Try every right-hand side in order, then (for commutative operators) the swapped operands
+			let mut attempts: Vec<TokenStream> = Vec::new();
+			for rhs in op
+				.operands
+				.0
+				.iter()
+				.map(|rhs: &Type| resolve(rhs, type_path))
+			{
+				attempts.push(quote! {
+					if let (Ok(a), Ok(b)) = (
+						<#type_path as mlua::FromLua>::from_lua(a.clone(), lua),
+						<#rhs as mlua::FromLua>::from_lua(b.clone(), lua),
+					) {
+						return mlua::IntoLua::into_lua(::std::ops::#trait_name::#method(a, b), lua);
+					};
+				});
+			}
+			if *commutative {
+				for rhs in op.operands.0.iter().filter(|rhs: &&Type| !is_self(rhs)) {
+					let rhs: Type = resolve(rhs, type_path);
+					attempts.push(quote! {
+						if let (Ok(a), Ok(b)) = (
+							<#rhs as mlua::FromLua>::from_lua(a.clone(), lua),
+							<#type_path as mlua::FromLua>::from_lua(b.clone(), lua),
+						) {
+							return mlua::IntoLua::into_lua(::std::ops::#trait_name::#method(b, a), lua);
+						};
+					});
+				}
+			};
+
+			registrations.push(quote! {
+				methods.add_meta_function(mlua::MetaMethod::#meta_method, |lua, (a, b): (mlua::Value, mlua::Value)| {
+					#(#attempts)*
+					return Err(mlua::Error::runtime(format!(
+						"attempt to perform '{}' on {} and {}",
+						#meta_method_str,
+						a.type_name(),
+						b.type_name(),
+					)));
+				});
+			});
+		}
+
+		if self.neg {
+			registrations.push(quote! {
+				methods.add_meta_function(mlua::MetaMethod::Unm, |_, value: #type_path| {
+					return Ok(::std::ops::Neg::neg(value));
+				});
+			});
+		};
+
+		// The accessor returns `None` for keys it has no value for, which Lua reads as `nil`
+		if let (Some(index), true) = (&self.index, with_index) {
+			registrations.push(quote! {
+				methods.add_meta_function(mlua::MetaMethod::Index, |lua, (user_data, key): (mlua::AnyUserData, mlua::Value)| {
+					let this: mlua::UserDataRef<#type_path> = user_data.borrow::<#type_path>()?;
+					return match mlua::FromLua::from_lua(key.clone(), lua) {
+						Ok(key) => mlua::IntoLua::into_lua(#index(&*this, key), lua),
+						Err(_) => Err(mlua::Error::runtime(format!("attempt to get an unknown field '{}'", key.to_string()?))),
+					};
+				});
+			});
+		};
+
+		return quote! { #(#registrations)* };
+	}
+
+	/// Markers claiming the metamethods that `#[implementation]` blocks or positional
+	/// fields could register too, see `implementation::export_marker`
+	pub fn export_markers(&self) -> Vec<Ident> {
+		return self
+			.index
+			.iter()
+			.map(|index: &Path| implementation::export_marker("__index", index.span()))
+			.collect();
+	}
+}
+
+fn is_self(ty: &Type) -> bool {
+	return matches!(ty, Type::Path(path) if path.qself.is_none() && path.path.is_ident("Self"));
+}
+
+/// `Self` means the compiled type, not the (projection) userdata the registration ends up on
+fn resolve(ty: &Type, type_path: &TypePath) -> Type {
+	if is_self(ty) {
+		return Type::Path(type_path.clone());
+	};
+
+	return ty.clone();
+}
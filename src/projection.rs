@@ -0,0 +1,134 @@
+use ::proc_macro2::TokenStream;
+
+use ::quote::quote;
+
+use ::syn::{Generics, TypePath};
+
+/// Bounds of a "place": a closure that borrows the root userdata and hands out
+/// `&mut Target` for as long as the callback runs.
+///
+/// Places are plain generic closures (not `Rc<dyn Fn>`) so projections stay
+/// `Send` whenever the root `mlua::AnyUserData` is.
+pub fn place_bounds(target: &TokenStream) -> TokenStream {
+	return quote! {
+		Fn(&mut dyn FnMut(&mut #target)) -> mlua::Result<()> + Clone + mlua::MaybeSend + 'static
+	};
+}
+
+/// Generates the projection userdata for `type_path`, plus the
+/// `_to_mlua_projection` constructor used by `#[lua(project)]` fields.
+///
+/// Named fields are projected through `_to_mlua_projected_fields`
+/// (so nested projections keep pointing at the root value). Everything else
+/// (methods, variants, ...) is forwarded to a temporary copy of the value
+/// which is written back through the place once the access is done.
+///
+/// `generics` are those of the `compile!` impl; the projection adds its place `P` to them.
+/// `meta_registrations` are the `compile!(meta(...), ops(...))` metamethods, working on a snapshot.
+pub fn projection_tokens(
+	type_path: &TypePath,
+	generics: &Generics,
+	fields: bool,
+	meta_registrations: &TokenStream,
+) -> TokenStream {
+	let place_bounds: TokenStream = place_bounds(&quote! { #type_path });
+	let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+	let mut projection_generics: Generics = generics.clone();
+	projection_generics.params.push(syn::parse_quote! { P });
+	projection_generics
+		.make_where_clause()
+		.predicates
+		.push(syn::parse_quote! { P: #place_bounds });
+	let (projection_impl_generics, _, projection_where_clause) =
+		projection_generics.split_for_impl();
+
+	let fields_call: TokenStream = if fields {
+		quote! {
+			<#type_path>::_to_mlua_projected_fields(fields, |projection: &Self| &projection.0);
+		}
+	} else {
+		quote! { /* Do nothing */ }
+	};
+
+	return quote! {
+		// `T` is the projected type, so generic instantiations do not overlap
+		struct Projection<T, P>(P, ::std::marker::PhantomData<fn() -> T>);
+
+		impl #projection_impl_generics Projection<#type_path, P> #projection_where_clause {
+			fn snapshot(place: &P) -> mlua::Result<#type_path> {
+				let mut snapshot: Option<#type_path> = None;
+				place(&mut |target: &mut #type_path| {
+					snapshot = Some(target.clone());
+				})?;
+
+				return snapshot.ok_or_else(|| mlua::Error::UserDataDestructed);
+			}
+
+			fn write_back(place: &P, value: #type_path) -> mlua::Result<()> {
+				let mut value: Option<#type_path> = Some(value);
+				return place(&mut |target: &mut #type_path| {
+					if let Some(value) = value.take() {
+						*target = value;
+					};
+				});
+			}
+		}
+
+		impl #projection_impl_generics mlua::UserData for Projection<#type_path, P> #projection_where_clause {
+			fn add_fields<F: mlua::UserDataFields<Self>>(fields: &mut F) -> () {
+				#fields_call
+			}
+
+			fn add_methods<M: mlua::UserDataMethods<Self>>(methods: &mut M) -> () {
+				// Lets `FromLua` turn a projection back into an owned value
+				methods.add_meta_method("__magic_snapshot", |lua, projection, ()| {
+					return lua.create_userdata(Self::snapshot(&projection.0)?);
+				});
+
+				#meta_registrations
+
+				// Anything that is not a projected field is looked up on a copy of the value
+				methods.add_meta_function(mlua::MetaMethod::Index, |lua, (projection, key): (mlua::UserDataRef<Self>, mlua::Value)| {
+					let target: mlua::AnyUserData = lua.create_userdata(Self::snapshot(&projection.0)?)?;
+					let value: mlua::Value = mlua::ObjectLike::get(&target, key)?;
+
+					let mlua::Value::Function(method) = value else {
+						return Ok(value);
+					};
+
+					// Methods run on the copy, which then replaces the projected value.
+					// Methods taking `self` refuse the copy, since there is nothing to write back.
+					let place: P = projection.0.clone();
+					let forward: mlua::Function = lua.create_function(move |lua, (_, args): (mlua::Value, mlua::MultiValue)| {
+						let target: mlua::AnyUserData = lua.create_userdata(Self::snapshot(&place)?)?;
+						target.set_named_user_value("__magic_projected", true)?;
+						let output: mlua::MultiValue = method.call((target.clone(), args))?;
+						Self::write_back(&place, target.take::<#type_path>()?)?;
+
+						return Ok(output);
+					})?;
+
+					return Ok(mlua::Value::Function(forward));
+				});
+
+				methods.add_meta_function(mlua::MetaMethod::NewIndex, |lua, (projection, key, value): (mlua::UserDataRef<Self>, mlua::Value, mlua::Value)| {
+					let target: mlua::AnyUserData = lua.create_userdata(Self::snapshot(&projection.0)?)?;
+					mlua::ObjectLike::set(&target, key, value)?;
+
+					return Self::write_back(&projection.0, target.take::<#type_path>()?);
+				});
+			}
+		}
+
+		impl #impl_generics #type_path #where_clause {
+			#[doc(hidden)]
+			pub fn _to_mlua_projection<P>(lua: &mlua::Lua, place: P) -> mlua::Result<mlua::AnyUserData>
+			where
+				P: #place_bounds,
+			{
+				return lua.create_userdata(Projection(place, ::std::marker::PhantomData));
+			}
+		}
+	};
+}
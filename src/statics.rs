@@ -0,0 +1,46 @@
+use ::proc_macro2::{Ident, TokenStream};
+
+use ::quote::quote;
+
+use ::syn::{Item, ItemMod, StaticMutability};
+
+use ::darling::Error;
+
+/// Registrations for the `static` and `const` items of a `#[statics]` module, as
+/// static fields named after the items. Values are cloned when the type is registered.
+pub fn registrations(module: &ItemMod) -> darling::Result<Vec<TokenStream>> {
+	let Some((_, items)) = &module.content else {
+		return Err(
+			Error::custom("`#[statics]` needs an inline module, `mod name { ... }`")
+				.with_span(&module.ident),
+		);
+	};
+
+	let mut errors: darling::error::Accumulator = Error::accumulator();
+	let mut registrations: Vec<TokenStream> = Vec::new();
+	for item in items {
+		let item_name: &Ident = match item {
+			Item::Static(item_static) => {
+				// A `static mut` cannot be read safely, and would only be copied once anyway
+				if !matches!(item_static.mutability, StaticMutability::None) {
+					errors.push(
+						Error::custom("`static mut` items cannot be exported to Lua")
+							.with_span(&item_static.ident),
+					);
+					continue;
+				};
+				&item_static.ident
+			}
+			Item::Const(item_const) => &item_const.ident,
+			_ => continue,
+		};
+		let item_name_str: String = item_name.to_string();
+
+		registrations.push(quote! {
+			fields.add_field(#item_name_str, ::std::clone::Clone::clone(&#item_name));
+		});
+	}
+	errors.finish()?;
+
+	return Ok(registrations);
+}
@@ -0,0 +1,97 @@
+use ::syn::{Field, Fields, Type, Visibility};
+
+use ::proc_macro::TokenStream;
+
+use ::proc_macro2::Ident;
+
+use ::darling::{Error, FromField, FromMeta, ast::NestedMeta};
+
+use crate::export::Export;
+
+/// Options given through `#[structure(...)]`
+#[derive(Debug, Default, FromMeta)]
+pub struct StructureArgs {
+	#[darling(default)]
+	pub positional: Positional,
+	/// Which fields are exported, `export = "pub"` or `export = "all"`
+	#[darling(default)]
+	pub export: Option<Export>,
+}
+
+/// How the fields of a tuple struct are reached from Lua
+#[derive(Debug, Default, Clone, Copy, PartialEq, FromMeta)]
+pub enum Positional {
+	/// `color[1]`, `color[2]`, ... (1-based, like Lua sequences)
+	#[default]
+	#[darling(rename = "index")]
+	Index,
+	/// `color._0`, `color._1`, ...
+	#[darling(rename = "named")]
+	Named,
+}
+
+/// Per-field options given through `#[lua(...)]` on a `#[structure]` field
+#[derive(Debug, FromField)]
+#[darling(attributes(lua))]
+pub struct FieldArgs {
+	pub ident: Option<Ident>,
+	pub vis: Visibility,
+	pub ty: Type,
+	#[darling(default)]
+	pub skip: bool,
+	/// Exports the field even if the `export` policy leaves it out
+	#[darling(default)]
+	pub export: bool,
+	#[darling(default)]
+	pub rename: Option<String>,
+	#[darling(default)]
+	pub readonly: bool,
+	#[darling(default)]
+	pub writeonly: bool,
+	#[darling(default)]
+	pub project: bool,
+}
+
+impl FieldArgs {
+	/// The name the field is exposed under in Lua,
+	/// or `None` when it is only reachable by position (`value[1]`)
+	pub fn lua_name(&self, index: usize, positional: Positional) -> Option<String> {
+		if let Some(rename) = &self.rename {
+			return Some(rename.clone());
+		};
+
+		return match (&self.ident, positional) {
+			(Some(ident), _) => Some(ident.to_string()),
+			(None, Positional::Named) => Some(format!("_{}", index)),
+			(None, Positional::Index) => None,
+		};
+	}
+}
+
+pub fn parse_field_args(field: &Field) -> darling::Result<FieldArgs> {
+	let args: FieldArgs = FieldArgs::from_field(field)?;
+
+	if args.skip && args.export {
+		return Err(Error::custom("a field cannot be both `skip` and `export`").with_span(field));
+	};
+	if args.readonly && args.writeonly {
+		return Err(
+			Error::custom("a field cannot be both `readonly` and `writeonly`").with_span(field),
+		);
+	};
+
+	return Ok(args);
+}
+
+/// Removes our `#[lua(...)]` helper attributes so the re-emitted struct still compiles
+pub fn strip_field_attrs(fields: &mut Fields) -> () {
+	for field in fields.iter_mut() {
+		field.attrs.retain(|attr| !attr.path().is_ident("lua"));
+	}
+}
+
+pub fn parse_structure_args(attr: TokenStream) -> darling::Result<StructureArgs> {
+	let items: Vec<NestedMeta> = NestedMeta::parse_meta_list(attr.into())?;
+
+	return StructureArgs::from_list(&items);
+}
@@ -0,0 +1,140 @@
+use ::proc_macro2::TokenStream;
+
+use ::quote::quote;
+
+use ::syn::{GenericArgument, Path, PathArguments, ReturnType, Type};
+
+use ::darling::FromMeta;
+
+/// Options of `throws`, on an `#[implementation]` block or one of its fns.
+///
+/// Both are paths to fns taking `&E`: `kind` returns something `Display`,
+/// `code` something `IntoLua`.
+#[derive(Debug, Default, Clone, FromMeta)]
+pub struct ThrowsArgs {
+	#[darling(default)]
+	pub kind: Option<Path>,
+	#[darling(default)]
+	pub code: Option<Path>,
+}
+
+/// `T` and `E` of a `Result<T, E>` return type.
+///
+/// `E` is `None` for aliases such as `mlua::Result<T>`;
+/// the outer `Option` is `None` if the fn does not return a `Result` at all.
+pub fn result_types(output: &ReturnType) -> Option<(&Type, Option<&Type>)> {
+	let ReturnType::Type(_, ty) = output else {
+		return None;
+	};
+	let Type::Path(type_path) = &**ty else {
+		return None;
+	};
+	let segment = type_path.path.segments.last()?;
+	if segment.ident != "Result" && segment.ident != "LuaResult" {
+		return None;
+	};
+	let PathArguments::AngleBracketed(args) = &segment.arguments else {
+		return None;
+	};
+
+	let mut types = args
+		.args
+		.iter()
+		.filter_map(|arg: &GenericArgument| match arg {
+			GenericArgument::Type(ty) => Some(ty),
+			_ => None,
+		});
+
+	return Some((types.next()?, types.next()));
+}
+
+/// Nested fns the registrations of a throwing method rely on, emitted once per helper
+pub fn support_fns() -> TokenStream {
+	return quote! {
+		// `{ kind, message, code }`, printing as its message
+		#[allow(dead_code)]
+		fn throw_table(lua: &mlua::Lua, kind: String, message: String, code: mlua::Value) -> mlua::Result<mlua::Table> {
+			let metatable: mlua::Table = match lua.named_registry_value::<Option<mlua::Table>>("__magic_error_metatable")? {
+				Some(metatable) => metatable,
+				None => {
+					let metatable: mlua::Table = lua.create_table()?;
+					metatable.set("__tostring", lua.create_function(|_, error: mlua::Table| error.get::<String>("message"))?)?;
+					lua.set_named_registry_value("__magic_error_metatable", &metatable)?;
+					metatable
+				}
+			};
+
+			let table: mlua::Table = lua.create_table()?;
+			table.set("kind", kind)?;
+			table.set("message", message)?;
+			table.set("code", code)?;
+			table.set_metatable(Some(metatable))?;
+
+			return Ok(table);
+		}
+
+		// Turns `raw`, returning `(true, ...)` or `(false, error)`, into a fn raising `error`.
+		// Rust callbacks cannot raise arbitrary Lua values, so this has to happen in Lua.
+		#[allow(dead_code)]
+		fn throwing(lua: &mlua::Lua, raw: mlua::Function) -> mlua::Result<mlua::Function> {
+			let factory: mlua::Function = match lua.named_registry_value::<Option<mlua::Function>>("__magic_throws")? {
+				Some(factory) => factory,
+				None => {
+					let factory: mlua::Function = lua
+						.load(r#"
+							local raw = ...
+							local function unwrap(ok, ...)
+								if ok then
+									return ...
+								end
+								error((...), 2)
+							end
+							return function(...)
+								return unwrap(raw(...))
+							end
+						"#)
+						.set_name("=throws")
+						.into_function()?;
+					lua.set_named_registry_value("__magic_throws", &factory)?;
+					factory
+				}
+			};
+
+			return factory.call::<mlua::Function>(raw);
+		}
+	};
+}
+
+/// Body of a raw registration: runs `invoke` (a `Result`) and returns `(true, value...)`
+/// or `(false, error table)`. Expects `lua` in scope.
+pub fn raw_body(invoke: &TokenStream, args: &ThrowsArgs) -> TokenStream {
+	let kind: TokenStream = match &args.kind {
+		Some(kind) => quote! { #kind(&error).to_string() },
+		// The variant (or type) name, taken from the `Debug` output
+		None => quote! {
+			format!("{:?}", error)
+				.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+				.next()
+				.unwrap_or_default()
+				.to_string()
+		},
+	};
+	let code: TokenStream = match &args.code {
+		Some(code) => quote! { mlua::IntoLua::into_lua(#code(&error), &lua)? },
+		None => quote! { mlua::Value::Nil },
+	};
+
+	return quote! {
+		return match #invoke {
+			Ok(value) => {
+				let mut values: mlua::MultiValue = mlua::IntoLuaMulti::into_lua_multi(value, &lua)?;
+				values.push_front(mlua::Value::Boolean(true));
+				Ok(values)
+			}
+			Err(error) => {
+				let table: mlua::Table = throw_table(&lua, #kind, error.to_string(), #code)?;
+				Ok(mlua::MultiValue::from_iter([mlua::Value::Boolean(false), mlua::Value::Table(table)]))
+			}
+		};
+	};
+}
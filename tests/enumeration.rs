@@ -0,0 +1,273 @@
+#[cfg(test)]
+pub mod enumeration {
+	use ::mlua::prelude::*;
+
+	use ::mlua_magic_macros;
+
+	#[derive(Debug, Clone, PartialEq)]
+	#[mlua_magic_macros::enumeration]
+	pub enum Status {
+		Idle,
+		OnFire,
+		Attacking(i32, String),
+		Move { dx: f32, dy: f32 },
+		Cast { spell: String, dx: f32 },
+	}
+
+	mlua_magic_macros::compile!(type_path = Status, variants = true);
+
+	#[derive(Debug, Clone, Copy, PartialEq)]
+	#[mlua_magic_macros::enumeration(unit_functions)]
+	pub enum Mode {
+		Easy,
+		Hard,
+	}
+
+	mlua_magic_macros::compile!(type_path = Mode, variants = true);
+
+	#[derive(Debug, Clone, Copy, PartialEq)]
+	#[mlua_magic_macros::enumeration]
+	pub enum Light {
+		On,
+		Off,
+	}
+
+	#[mlua_magic_macros::implementation]
+	impl Light {
+		pub fn toggle(&mut self) -> () {
+			*self = match self {
+				Light::On => Light::Off,
+				Light::Off => Light::On,
+			};
+		}
+
+		pub fn into_name(self) -> String {
+			return format!("{:?}", self);
+		}
+
+		pub fn flipped(mut light: Light) -> Light {
+			light.toggle();
+			return light;
+		}
+	}
+
+	mlua_magic_macros::compile!(type_path = Light, variants = true, methods = true);
+
+	#[derive(Debug, Clone, Copy, Default, PartialEq)]
+	#[mlua_magic_macros::enumeration(repr = "integer")]
+	pub enum Layer {
+		#[default]
+		Ground = 0,
+		Air = 1,
+		Space = 10,
+	}
+
+	#[derive(Debug, Clone, Copy, Default, PartialEq)]
+	#[mlua_magic_macros::enumeration(repr = "string")]
+	pub enum Weather {
+		#[default]
+		Sun,
+		Rain,
+	}
+
+	#[derive(Debug, Clone, PartialEq)]
+	#[mlua_magic_macros::enumeration(repr = "table")]
+	pub enum Message {
+		Ping,
+		Attack(i32, String),
+		Move { dx: f32, dy: f32 },
+	}
+
+	#[derive(Debug, Clone, Default)]
+	#[mlua_magic_macros::structure]
+	pub struct Tile {
+		layer: Layer,
+		weather: Weather,
+	}
+
+	mlua_magic_macros::compile!(type_path = Tile, fields = true);
+
+	#[test]
+	fn variant_introspection() -> LuaResult<()> {
+		let lua: Lua = Lua::new();
+		mlua_magic_macros::load!(lua, Status);
+
+		lua.load(
+			r#"
+				local idle = Status.Idle
+				assert(idle.kind == "Idle" and idle:is_idle() and not idle:is_on_fire())
+				assert(Status.OnFire:is_on_fire())
+
+				local attacking = Status.Attacking(20, "sword")
+				assert(attacking.kind == "Attacking" and attacking:is_attacking())
+				assert(attacking[1] == 20 and attacking[2] == "sword")
+
+				local move = Status.Move{ dx = 1, dy = 2 }
+				assert(move.kind == "Move" and move.dx == 1 and move.dy == 2)
+				assert(Status.Cast{ spell = "fireball", dx = 3 }.dx == 3)
+
+				local ok, err = pcall(function() return idle.dx end)
+				assert(not ok and tostring(err):find("Status.Idle has no field 'dx'", 1, true))
+
+				local ok, err = pcall(function() return attacking[3] end)
+				assert(not ok and tostring(err):find("Status.Attacking has no value at index 3", 1, true))
+
+				local ok, err = pcall(function() return move[1] end)
+				assert(not ok and tostring(err):find("Status.Move has no value at index 1", 1, true))
+
+				last = move
+			"#,
+		)
+		.exec()?;
+
+		assert_eq!(
+			lua.globals().get::<Status>("last")?,
+			Status::Move { dx: 1.0, dy: 2.0 }
+		);
+
+		return Ok(());
+	}
+
+	#[test]
+	fn lua_match() -> LuaResult<()> {
+		let lua: Lua = Lua::new();
+		mlua_magic_macros::load!(lua, Status);
+
+		lua.load(
+			r#"
+				local function describe(status)
+					return status:match{
+						Idle = function() return "idle" end,
+						Attacking = function(damage, weapon) return weapon .. " " .. damage end,
+						Move = function(dx, dy) return dx + dy end,
+						_ = function() return "other" end,
+					}
+				end
+				assert(describe(Status.Idle) == "idle")
+				assert(describe(Status.Attacking(20, "sword")) == "sword 20")
+				assert(describe(Status.Move{ dx = 1, dy = 2 }) == 3)
+				assert(describe(Status.OnFire) == "other")
+
+				local ok, err = pcall(function()
+					return Status.Idle:match{ Idle = function() end, Attacking = function() end }
+				end)
+				assert(not ok and tostring(err):find("non-exhaustive match on Status, missing: OnFire, Move, Cast", 1, true))
+
+				local ok, err = pcall(function()
+					return Status.Idle:match{ Idel = function() end, _ = function() end }
+				end)
+				assert(not ok and tostring(err):find("Status has no variant 'Idel'", 1, true))
+			"#,
+		)
+		.exec()?;
+
+		return Ok(());
+	}
+
+	#[test]
+	fn unit_variants() -> LuaResult<()> {
+		let lua: Lua = Lua::new();
+		mlua_magic_macros::load!(lua, Status, Mode, Light);
+
+		lua.load(
+			r#"
+				assert(Status.Idle == Status.Idle and Status.Idle ~= Status.OnFire)
+				assert(Status.Idle.kind == "Idle")
+				assert(Status.Attacking(1, "axe") ~= Status.Attacking(1, "axe"))
+				idle = Status.Idle
+
+				assert(Mode.Easy():is_easy() and Mode.Hard().kind == "Hard")
+
+				-- Unit variants are one shared value, usable as a table key
+				local names = { [Status.Idle] = "idle" }
+				assert(names[Status.Idle] == "idle" and rawequal(Status.Idle, Status.Idle))
+
+				-- ... which methods cannot change or take
+				local light = Light.Off
+				assert(not pcall(light.toggle, light))
+				assert(not pcall(Light.On.into_name, Light.On))
+				assert(Light.Off.kind == "Off" and Light.On.kind == "On")
+				assert(Light.Off:is_off() and Light.Off ~= Light.On)
+
+				-- Copies made in Rust are their own values, equal to the shared one
+				local copy = Light.flipped(Light.On)
+				copy:toggle()
+				assert(copy == Light.On and not rawequal(copy, Light.On))
+			"#,
+		)
+		.exec()?;
+
+		assert_eq!(lua.globals().get::<Status>("idle")?, Status::Idle);
+
+		return Ok(());
+	}
+
+	#[test]
+	fn plain_representations() -> LuaResult<()> {
+		let lua: Lua = Lua::new();
+		lua.globals().set("tile", Tile::default())?;
+
+		lua.load(
+			r#"
+				assert(tile.layer == 0 and tile.weather == "Sun")
+				tile.layer = 10
+				tile.weather = "Rain"
+
+				local ok, err = pcall(function() tile.layer = 2 end)
+				assert(not ok and tostring(err):find("unknown value 2, expected one of: 0 (Ground), 1 (Air), 10 (Space)", 1, true))
+
+				local ok, err = pcall(function() tile.weather = "Snow" end)
+				assert(not ok and tostring(err):find("unknown variant 'Snow', expected one of: Sun, Rain", 1, true))
+			"#,
+		)
+		.exec()?;
+
+		let tile: Tile = lua.globals().get("tile")?;
+		assert_eq!(tile.layer, Layer::Space);
+		assert_eq!(tile.weather, Weather::Rain);
+
+		return Ok(());
+	}
+
+	#[test]
+	fn table_representation() -> LuaResult<()> {
+		let lua: Lua = Lua::new();
+		lua.globals()
+			.set("attack", Message::Attack(20, "sword".to_string()))?;
+		lua.globals()
+			.set("move", Message::Move { dx: 1.0, dy: 2.0 })?;
+		lua.globals().set("ping", Message::Ping)?;
+
+		lua.load(
+			r#"
+				assert(attack.tag == "Attack" and attack[1] == 20 and attack[2] == "sword")
+				assert(move.tag == "Move" and move.dx == 1 and move.dy == 2)
+				assert(ping.tag == "Ping" and next(ping, next(ping)) == nil)
+
+				built = { tag = "Move", dx = 3, dy = 4 }
+				unknown = { tag = "Wave" }
+				broken = { tag = "Attack", 20 }
+			"#,
+		)
+		.exec()?;
+
+		assert_eq!(
+			lua.globals().get::<Message>("built")?,
+			Message::Move { dx: 3.0, dy: 4.0 }
+		);
+		let unknown: String = lua
+			.globals()
+			.get::<Message>("unknown")
+			.unwrap_err()
+			.to_string();
+		assert!(unknown.contains("unknown tag 'Wave', expected one of: Ping, Attack, Move"));
+		let broken: String = lua
+			.globals()
+			.get::<Message>("broken")
+			.unwrap_err()
+			.to_string();
+		assert!(broken.contains("bad field '2' of Message.Attack"));
+
+		return Ok(());
+	}
+}
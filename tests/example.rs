@@ -97,7 +97,7 @@ pub mod example {
 			// # This is the Lua script we will run ---
 			let lua_script: &str = r#"
                 -- Call the static `new` function we registered
-                print(PlayerStatus.Idle());
+                print(PlayerStatus.Idle);
                 player = Player.new("LuaHero");
                 print("Player created:");
 
@@ -0,0 +1,103 @@
+#[cfg(test)]
+pub mod generics {
+	use ::mlua::prelude::*;
+
+	use ::mlua_magic_macros;
+
+	#[derive(Debug, Clone, Default, PartialEq)]
+	#[mlua_magic_macros::structure]
+	pub struct Grid<T: Clone> {
+		width: usize,
+		cells: Vec<T>,
+	}
+
+	#[mlua_magic_macros::implementation]
+	impl<T: Clone> Grid<T> {
+		pub fn new(width: usize, height: usize, fill: T) -> Self {
+			return Self {
+				width: width,
+				cells: vec![fill; width * height],
+			};
+		}
+
+		pub fn get(&self, x: usize, y: usize) -> T {
+			return self.cells[y * self.width + x].clone();
+		}
+
+		pub fn set(&mut self, x: usize, y: usize, value: T) -> () {
+			self.cells[y * self.width + x] = value;
+		}
+	}
+
+	// A concrete instantiation
+	mlua_magic_macros::compile!(type_path = Grid<f32>, fields = true, methods = true);
+
+	#[derive(Debug, Clone, PartialEq)]
+	#[mlua_magic_macros::structure]
+	pub struct Pair<T>(T, T);
+
+	// A generic impl for every `T` that converts to and from Lua
+	mlua_magic_macros::compile!(type_path = Pair<T>, generics = <T>, fields = true);
+
+	#[derive(Debug, Clone, Copy, PartialEq)]
+	#[mlua_magic_macros::enumeration]
+	pub enum Shape<N> {
+		Empty,
+		Circle(N),
+		Rect(N, N),
+	}
+
+	#[mlua_magic_macros::implementation]
+	impl<N: Copy + Into<f64>> Shape<N> {
+		pub fn area(&self) -> f64 {
+			return match *self {
+				Self::Empty => 0.0,
+				Self::Circle(r) => 3.0 * r.into() * r.into(),
+				Self::Rect(w, h) => w.into() * h.into(),
+			};
+		}
+	}
+
+	mlua_magic_macros::compile!(
+		type_path = Shape<N>,
+		generics = <N>,
+		bounds = "N: Copy + Into<f64>",
+		variants = true,
+		methods = true
+	);
+
+	#[test]
+	fn generic_types() -> LuaResult<()> {
+		let lua: Lua = Lua::new();
+		mlua_magic_macros::load!(lua, Grid<f32> as Grid, Shape<u8> as Shape);
+		lua.globals()
+			.set("pair", Pair("a".to_string(), "b".to_string()))?;
+
+		lua.load(
+			r#"
+				grid = Grid.new(2, 2, 0.5)
+				grid:set(1, 1, 4.0)
+				assert(grid:get(1, 1) == 4.0)
+				assert(grid.width == 2)
+
+				assert(pair[1] == "a")
+				pair[2] = "c"
+
+				assert(Shape.Empty:area() == 0)
+				assert(Shape.Rect(2, 3):area() == 6)
+				shape = Shape.Circle(2)
+			"#,
+		)
+		.exec()?;
+
+		let grid: Grid<f32> = lua.globals().get("grid")?;
+		assert_eq!(grid.cells, vec![0.5, 0.5, 0.5, 4.0]);
+		assert_eq!(
+			lua.globals().get::<Pair<String>>("pair")?,
+			Pair("a".to_string(), "c".to_string())
+		);
+		assert_eq!(lua.globals().get::<Shape<u8>>("shape")?, Shape::Circle(2));
+
+		return Ok(());
+	}
+}
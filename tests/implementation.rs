@@ -0,0 +1,922 @@
+#[cfg(test)]
+pub mod implementation {
+	use ::mlua::prelude::*;
+
+	use ::mlua_magic_macros;
+
+	#[derive(Debug, Clone, Default)]
+	#[mlua_magic_macros::structure]
+	pub struct Npc {
+		hp: i32,
+		items: Vec<String>,
+	}
+
+	#[mlua_magic_macros::implementation]
+	impl Npc {
+		pub fn new(hp: i32) -> Self {
+			return Self {
+				hp: hp,
+				items: Vec::new(),
+			};
+		}
+	}
+
+	#[mlua_magic_macros::implementation(tag = "combat")]
+	impl Npc {
+		pub const MAX_HP: i32 = 100;
+
+		pub fn damage(&mut self, amount: i32) -> () {
+			self.hp -= amount;
+		}
+
+		pub fn is_alive(&self) -> bool {
+			return self.hp > 0;
+		}
+	}
+
+	#[mlua_magic_macros::implementation(tag = "inventory")]
+	impl Npc {
+		pub fn give(&mut self, item: String) -> () {
+			self.items.push(item);
+		}
+	}
+
+	pub trait Damageable {
+		fn health(&self) -> i32;
+		fn heal(&mut self, amount: i32) -> ();
+		fn spawn(hp: i32) -> Self;
+
+		const KIND: &'static str;
+	}
+
+	#[mlua_magic_macros::implementation]
+	impl Damageable for Npc {
+		fn health(&self) -> i32 {
+			return self.hp;
+		}
+
+		fn heal(&mut self, amount: i32) -> () {
+			self.hp += amount;
+		}
+
+		fn spawn(hp: i32) -> Self {
+			return Self::new(hp);
+		}
+
+		const KIND: &'static str = "npc";
+	}
+
+	#[mlua_magic_macros::statics]
+	mod npc_config {
+		pub static VERSION: &str = "1.2";
+		pub const NAMES: [&str; 2] = ["Orc", "Elf"];
+	}
+
+	mlua_magic_macros::compile!(
+		type_path = Npc,
+		fields = true,
+		methods = true,
+		tags(combat, inventory, Damageable),
+		statics(npc_config)
+	);
+
+	#[derive(Debug, Clone, Default)]
+	pub struct Turret {
+		armor: i32,
+	}
+
+	#[mlua_magic_macros::implementation]
+	impl Damageable for Turret {
+		fn health(&self) -> i32 {
+			return self.armor * 2;
+		}
+
+		fn heal(&mut self, amount: i32) -> () {
+			self.armor += amount / 2;
+		}
+
+		fn spawn(hp: i32) -> Self {
+			return Self { armor: hp / 2 };
+		}
+
+		const KIND: &'static str = "turret";
+	}
+
+	#[mlua_magic_macros::implementation]
+	impl ::std::fmt::Display for Turret {
+		fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+			return write!(f, "Turret({} armor)", self.armor);
+		}
+	}
+
+	mlua_magic_macros::compile!(type_path = Turret, tags(Damageable, Display));
+
+	#[derive(Debug, Clone, Default)]
+	pub struct Deck {
+		cards: Vec<String>,
+	}
+
+	#[mlua_magic_macros::implementation]
+	impl Deck {
+		pub fn new(cards: Vec<String>) -> Self {
+			return Self { cards: cards };
+		}
+
+		#[lua(meta = "__len")]
+		pub fn size(&self) -> usize {
+			return self.cards.len();
+		}
+
+		#[lua(meta = "__call")]
+		pub fn draw(&mut self) -> Option<String> {
+			return self.cards.pop();
+		}
+
+		#[lua(meta = "__concat")]
+		pub fn join(a: Deck, b: Deck) -> Deck {
+			return Deck {
+				cards: [a.cards, b.cards].concat(),
+			};
+		}
+	}
+
+	mlua_magic_macros::compile!(type_path = Deck, methods = true);
+
+	#[derive(Debug)]
+	pub enum BankError {
+		Insufficient { missing: u32 },
+		Frozen,
+	}
+
+	impl ::std::fmt::Display for BankError {
+		fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+			return match self {
+				BankError::Insufficient { missing } => write!(f, "missing {} gold", missing),
+				BankError::Frozen => write!(f, "account is frozen"),
+			};
+		}
+	}
+
+	pub fn bank_error_code(error: &BankError) -> u32 {
+		return match error {
+			BankError::Insufficient { .. } => 402,
+			BankError::Frozen => 423,
+		};
+	}
+
+	#[derive(Debug, Clone, Default)]
+	pub struct Bank {
+		gold: u32,
+		frozen: bool,
+	}
+
+	#[mlua_magic_macros::implementation(throws)]
+	impl Bank {
+		pub fn open(gold: u32) -> Result<Self, BankError> {
+			return Ok(Self {
+				gold: gold,
+				frozen: false,
+			});
+		}
+
+		pub fn freeze(&mut self) -> () {
+			self.frozen = true;
+		}
+
+		#[lua(throws(code = bank_error_code))]
+		pub fn withdraw(&mut self, amount: u32) -> Result<u32, BankError> {
+			if self.frozen {
+				return Err(BankError::Frozen);
+			};
+			if amount > self.gold {
+				return Err(BankError::Insufficient {
+					missing: amount - self.gold,
+				});
+			};
+
+			self.gold -= amount;
+			return Ok(self.gold);
+		}
+	}
+
+	mlua_magic_macros::compile!(type_path = Bank, methods = true);
+
+	#[derive(Debug, Clone, Default)]
+	#[mlua_magic_macros::structure]
+	pub struct Corpse {
+		name: String,
+	}
+
+	mlua_magic_macros::compile!(type_path = Corpse, fields = true);
+
+	#[derive(Debug, Clone, Default)]
+	#[mlua_magic_macros::structure]
+	pub struct Knight {
+		name: String,
+		level: u32,
+	}
+
+	#[mlua_magic_macros::implementation]
+	impl Knight {
+		pub fn new(name: String) -> Self {
+			return Self {
+				name: name,
+				level: 1,
+			};
+		}
+
+		pub fn into_corpse(self) -> Corpse {
+			return Corpse { name: self.name };
+		}
+
+		#[lua(clone)]
+		pub fn promoted(mut self) -> Self {
+			self.level += 1;
+			return self;
+		}
+	}
+
+	mlua_magic_macros::compile!(type_path = Knight, fields = true, methods = true);
+
+	#[derive(Debug, Clone, Copy, Default, PartialEq)]
+	#[mlua_magic_macros::structure]
+	pub struct Cursor {
+		x: f32,
+		y: f32,
+	}
+
+	#[mlua_magic_macros::implementation]
+	impl Cursor {
+		pub fn moved(&self, (dx, dy): (f32, f32), _: i32, Cursor { x, .. }: Cursor) -> Self {
+			return Self {
+				x: self.x + dx + x,
+				y: self.y + dy,
+			};
+		}
+
+		pub fn reset(&mut self, _reason: String) -> () {
+			*self = Self::default();
+		}
+	}
+
+	mlua_magic_macros::compile!(type_path = Cursor, fields = true, methods = true);
+
+	#[derive(Debug, Clone, Default)]
+	#[mlua_magic_macros::structure]
+	pub struct Duelist {
+		name: String,
+		hp: i32,
+	}
+
+	#[mlua_magic_macros::implementation]
+	impl Duelist {
+		pub fn new(name: &str) -> Self {
+			return Self {
+				name: name.to_string(),
+				hp: 10,
+			};
+		}
+
+		pub fn greet(&self, other: &Duelist, greeting: &str) -> String {
+			return format!("{}, {}! I am {}.", greeting, other.name, self.name);
+		}
+
+		pub fn attack(&self, target: &mut Duelist, rolls: &[i32]) -> () {
+			target.hp -= rolls.iter().sum::<i32>();
+		}
+
+		pub fn checksum(data: &[u8]) -> usize {
+			return data.len();
+		}
+	}
+
+	mlua_magic_macros::compile!(type_path = Duelist, fields = true, methods = true);
+
+	#[derive(Debug, Clone, Default, PartialEq)]
+	#[mlua_magic_macros::structure]
+	pub struct Recruit {
+		name: String,
+		hp: i32,
+		speed: f32,
+		title: String,
+	}
+
+	#[mlua_magic_macros::implementation]
+	impl Recruit {
+		pub fn new(
+			name: String,
+			#[lua(default = 100)] hp: i32,
+			#[lua(default = 1.5)] speed: f32,
+			#[lua(default = "Squire")] title: String,
+		) -> Self {
+			return Self {
+				name: name,
+				hp: hp,
+				speed: speed,
+				title: title,
+			};
+		}
+	}
+
+	#[mlua_magic_macros::implementation(tag = "keywords")]
+	impl Recruit {
+		#[lua(table_args)]
+		pub fn enlist(
+			name: String,
+			#[lua(default = 50)] hp: i32,
+			#[lua(default = 1.0)] speed: f32,
+		) -> Self {
+			return Self::new(name, hp, speed, "Recruit".to_string());
+		}
+
+		#[lua(table_args)]
+		pub fn train(&mut self, hp: i32, _note: Option<String>) -> () {
+			self.hp += hp;
+		}
+	}
+
+	mlua_magic_macros::compile!(
+		type_path = Recruit,
+		fields = true,
+		methods = true,
+		tags(keywords)
+	);
+
+	#[derive(Debug, Clone, Default)]
+	#[mlua_magic_macros::structure]
+	pub struct Inventory {
+		items: Vec<String>,
+	}
+
+	#[mlua_magic_macros::implementation]
+	impl Inventory {
+		pub fn filter(&self, keep: impl Fn(&String) -> LuaResult<bool>) -> LuaResult<Vec<String>> {
+			let mut kept: Vec<String> = Vec::new();
+			for item in &self.items {
+				if keep(item)? {
+					kept.push(item.clone());
+				};
+			}
+
+			return Ok(kept);
+		}
+
+		pub fn count(&self, each: &mut dyn FnMut(usize, String) -> LuaResult<()>) -> LuaResult<()> {
+			for (index, item) in self.items.iter().enumerate() {
+				each(index + 1, item.clone())?;
+			}
+
+			return Ok(());
+		}
+
+		pub fn rename(&mut self, name: impl Fn(String) -> LuaResult<String>) -> LuaResult<()> {
+			for item in self.items.iter_mut() {
+				*item = name(item.clone())?;
+			}
+
+			return Ok(());
+		}
+	}
+
+	mlua_magic_macros::compile!(type_path = Inventory, fields = true, methods = true);
+
+	#[derive(Debug, Clone, Default)]
+	#[mlua_magic_macros::structure]
+	pub struct Bag {
+		items: Vec<String>,
+	}
+
+	#[mlua_magic_macros::implementation]
+	impl Bag {
+		#[lua(iter)]
+		pub fn each(&self) -> impl Iterator<Item = &str> {
+			return self.items.iter().map(String::as_str);
+		}
+
+		#[lua(iter)]
+		pub fn indexed(&self) -> impl Iterator<Item = (usize, &str)> {
+			return self
+				.items
+				.iter()
+				.map(String::as_str)
+				.enumerate()
+				.map(|(index, item)| (index + 1, item));
+		}
+
+		#[lua(iter)]
+		pub fn lengths(&self) -> Vec<usize> {
+			return self.items.iter().map(String::len).collect();
+		}
+
+		#[lua(iter)]
+		pub fn countdown(from: u32) -> impl Iterator<Item = u32> {
+			return (1..=from).rev();
+		}
+
+		pub fn add(&mut self, item: String) -> () {
+			self.items.push(item);
+		}
+	}
+
+	mlua_magic_macros::compile!(type_path = Bag, fields = true, methods = true);
+
+	#[derive(Debug, Clone, Default, PartialEq)]
+	#[mlua_magic_macros::structure]
+	pub struct Span {
+		low: f32,
+		high: f32,
+	}
+
+	#[mlua_magic_macros::implementation]
+	impl Span {
+		#[lua(name = "new")]
+		pub fn empty() -> Self {
+			return Self::default();
+		}
+
+		#[lua(name = "new")]
+		pub fn to(high: f32) -> Self {
+			return Self::between(0.0, high);
+		}
+
+		#[lua(name = "new")]
+		pub fn parse(text: &str) -> Option<Self> {
+			let (low, high) = text.split_once("..")?;
+			return Some(Self::between(low.parse().ok()?, high.parse().ok()?));
+		}
+
+		#[lua(name = "new")]
+		pub fn between(low: f32, high: f32) -> Self {
+			return Self {
+				low: low,
+				high: high,
+			};
+		}
+
+		pub fn contains(&self, x: f32) -> bool {
+			return self.low <= x && x <= self.high;
+		}
+
+		#[lua(name = "contains")]
+		pub fn contains_span(&self, other: &Span) -> bool {
+			return self.low <= other.low && other.high <= self.high;
+		}
+
+		pub fn shift(&mut self, by: f32) -> () {
+			self.low += by;
+			self.high += by;
+		}
+
+		#[lua(name = "shift")]
+		pub fn shift_highs(&mut self, low: f32, high: f32) -> () {
+			self.low += low;
+			self.high += high;
+		}
+
+		pub fn widened(&self, #[lua(default = 1.0)] by: f32) -> Self {
+			return Self::between(self.low - by, self.high + by);
+		}
+
+		#[lua(name = "widened")]
+		pub fn widened_to(&self, other: &Span) -> Self {
+			return Self::between(self.low.min(other.low), self.high.max(other.high));
+		}
+	}
+
+	mlua_magic_macros::compile!(type_path = Span, fields = true, methods = true);
+
+	#[derive(Debug, Clone, Default)]
+	#[mlua_magic_macros::structure]
+	pub struct Vault {
+		gold: u32,
+	}
+
+	#[mlua_magic_macros::implementation(export = "pub")]
+	impl Vault {
+		pub const CAPACITY: u32 = 1000;
+		const FEE: u32 = 1;
+
+		pub fn new() -> Self {
+			return Self::default();
+		}
+
+		pub(crate) fn deposit(&mut self, amount: u32) -> () {
+			self.gold = (self.gold + amount - Self::FEE).min(Self::CAPACITY);
+			self.audit();
+		}
+
+		fn audit(&self) -> () {
+			assert!(self.gold <= Self::CAPACITY);
+		}
+
+		#[lua(export)]
+		fn peek(&self) -> u32 {
+			return self.gold;
+		}
+
+		#[lua(skip)]
+		pub fn empty(&mut self) -> () {
+			self.gold = 0;
+		}
+	}
+
+	mlua_magic_macros::compile!(type_path = Vault, fields = true, methods = true);
+
+	#[test]
+	fn tagged_blocks() -> LuaResult<()> {
+		let lua: Lua = Lua::new();
+		mlua_magic_macros::load!(lua, Npc);
+
+		lua.load(
+			r#"
+				npc = Npc.new(10)
+				npc:damage(4)
+				npc:give("apple")
+				assert(npc:is_alive())
+			"#,
+		)
+		.exec()?;
+
+		let npc: Npc = lua.globals().get("npc")?;
+		assert_eq!(npc.hp, 6);
+		assert_eq!(npc.items, vec!["apple"]);
+
+		return Ok(());
+	}
+
+	#[test]
+	fn constants() -> LuaResult<()> {
+		let lua: Lua = Lua::new();
+		mlua_magic_macros::load!(lua, Npc);
+
+		lua.load(
+			r#"
+				assert(Npc.MAX_HP == 100 and Npc.KIND == "npc")
+				assert(Npc.VERSION == "1.2" and Npc.NAMES[2] == "Elf")
+
+				local npc = Npc.new(10)
+				assert(npc.MAX_HP == 100)
+			"#,
+		)
+		.exec()?;
+
+		return Ok(());
+	}
+
+	#[test]
+	fn trait_impls() -> LuaResult<()> {
+		let lua: Lua = Lua::new();
+		mlua_magic_macros::load!(lua, Npc, Turret);
+
+		lua.load(
+			r#"
+				for _, entity in ipairs({ Npc.spawn(10), Turret.spawn(10) }) do
+					entity:heal(4)
+					assert(entity:health() == 14)
+				end
+
+				assert(tostring(Turret.spawn(10)) == "Turret(5 armor)")
+				assert(Turret.spawn(10).fmt == nil)
+			"#,
+		)
+		.exec()?;
+
+		return Ok(());
+	}
+
+	#[test]
+	fn meta_methods() -> LuaResult<()> {
+		let lua: Lua = Lua::new();
+		mlua_magic_macros::load!(lua, Deck);
+
+		lua.load(
+			r#"
+				local deck = Deck.new({ "ace", "king" }) .. Deck.new({ "queen" })
+				assert(#deck == 3)
+				assert(deck() == "queen")
+				assert(#deck == 2)
+				assert(not pcall(function() return deck:size() end))
+			"#,
+		)
+		.exec()?;
+
+		return Ok(());
+	}
+
+	#[test]
+	fn throws() -> LuaResult<()> {
+		let lua: Lua = Lua::new();
+		mlua_magic_macros::load!(lua, Bank);
+
+		lua.load(
+			r#"
+				local bank = Bank.open(10)
+				assert(bank:withdraw(4) == 6)
+
+				local ok, err = pcall(bank.withdraw, bank, 10)
+				assert(not ok)
+				assert(err.kind == "Insufficient")
+				assert(err.message == "missing 4 gold")
+				assert(err.code == 402)
+				assert(tostring(err) == "missing 4 gold")
+
+				bank:freeze()
+				local ok, err = pcall(function() return bank:withdraw(1) end)
+				assert(not ok and err.kind == "Frozen" and err.code == 423)
+
+				-- Scripts only ever see the wrapper raising the error
+				assert(bank.withdraw == Bank.open(1).withdraw)
+				assert(bank.__magic_raw_withdraw == nil)
+			"#,
+		)
+		.exec()?;
+
+		// Uncaught, the error reads as its message
+		let error: LuaError = lua.load("Bank.open(1):withdraw(5)").exec().unwrap_err();
+		assert!(error.to_string().contains("missing 4 gold"), "{}", error);
+
+		return Ok(());
+	}
+
+	#[test]
+	fn consuming_methods() -> LuaResult<()> {
+		let lua: Lua = Lua::new();
+		mlua_magic_macros::load!(lua, Knight);
+
+		lua.load(
+			r#"
+				local knight = Knight.new("Arthur")
+				local promoted = knight:promoted()
+				assert(promoted.level == 2 and knight.level == 1)
+
+				local corpse = knight:into_corpse()
+				assert(corpse.name == "Arthur")
+
+				-- The knight was moved into the corpse
+				local ok, err = pcall(function() return knight.name end)
+				assert(not ok)
+				assert(not pcall(function() return knight:into_corpse() end))
+				assert(promoted.name == "Arthur")
+			"#,
+		)
+		.exec()?;
+
+		return Ok(());
+	}
+
+	#[test]
+	fn argument_patterns() -> LuaResult<()> {
+		let lua: Lua = Lua::new();
+		lua.globals().set("cursor", Cursor { x: 1.0, y: 1.0 })?;
+		lua.globals().set("offset", Cursor { x: 10.0, y: 0.0 })?;
+
+		lua.load(
+			r#"
+				local moved = cursor:moved(2, 3, 0, offset)
+				assert(moved.x == 13 and moved.y == 4)
+				cursor:reset("test")
+			"#,
+		)
+		.exec()?;
+
+		assert_eq!(lua.globals().get::<Cursor>("cursor")?, Cursor::default());
+
+		return Ok(());
+	}
+
+	#[test]
+	fn borrowed_arguments() -> LuaResult<()> {
+		let lua: Lua = Lua::new();
+		mlua_magic_macros::load!(lua, Duelist);
+
+		lua.load(
+			r#"
+				local a = Duelist.new("Ann")
+				local b = Duelist.new("Bob")
+				assert(a:greet(b, "Hello") == "Hello, Bob! I am Ann.")
+				a:attack(b, { 1, 2, 3 })
+				assert(b.hp == 4)
+				assert(Duelist.checksum("abc") == 3)
+
+				-- `target` cannot be borrowed mutably while `self` is borrowed
+				local ok, err = pcall(function() a:attack(a, { 1 }) end)
+				assert(not ok and tostring(err):find("borrow"))
+			"#,
+		)
+		.exec()?;
+
+		return Ok(());
+	}
+
+	#[test]
+	fn default_arguments() -> LuaResult<()> {
+		let lua: Lua = Lua::new();
+		mlua_magic_macros::load!(lua, Recruit);
+
+		lua.load(
+			r#"
+				a = Recruit.new("Hero")
+				b = Recruit.new("Tank", 300, nil, "Knight")
+			"#,
+		)
+		.exec()?;
+
+		assert_eq!(
+			lua.globals().get::<Recruit>("a")?,
+			Recruit {
+				name: "Hero".to_string(),
+				hp: 100,
+				speed: 1.5,
+				title: "Squire".to_string(),
+			}
+		);
+		assert_eq!(
+			lua.globals().get::<Recruit>("b")?,
+			Recruit {
+				name: "Tank".to_string(),
+				hp: 300,
+				speed: 1.5,
+				title: "Knight".to_string(),
+			}
+		);
+
+		return Ok(());
+	}
+
+	#[test]
+	fn table_arguments() -> LuaResult<()> {
+		let lua: Lua = Lua::new();
+		mlua_magic_macros::load!(lua, Recruit);
+
+		lua.load(
+			r#"
+				recruit = Recruit.enlist{ name = "Orc", speed = 2 }
+				recruit:train{ hp = 5 }
+				assert(recruit.hp == 55 and recruit.speed == 2)
+
+				local ok, err = pcall(Recruit.enlist, { name = "Orc", hp = "lots" })
+				assert(not ok and tostring(err):find("bad argument 'hp' to 'enlist'", 1, true))
+
+				local ok, err = pcall(Recruit.enlist, { name = "Orc", hpp = 1 })
+				assert(not ok and tostring(err):find("unknown argument 'hpp' to 'enlist'", 1, true))
+			"#,
+		)
+		.exec()?;
+
+		return Ok(());
+	}
+
+	#[test]
+	fn callback_arguments() -> LuaResult<()> {
+		let lua: Lua = Lua::new();
+		lua.globals().set(
+			"inventory",
+			Inventory {
+				items: vec!["apple".to_string(), "axe".to_string(), "bread".to_string()],
+			},
+		)?;
+
+		lua.load(
+			r#"
+				local kept = inventory:filter(function(item) return item:sub(1, 1) == "a" end)
+				assert(#kept == 2 and kept[2] == "axe")
+
+				local seen = {}
+				inventory:count(function(index, item) seen[index] = item end)
+				assert(seen[3] == "bread")
+
+				local kept, err = inventory:filter(function(item) error("bad item") end)
+				assert(kept == nil and tostring(err):find("bad item", 1, true))
+
+				inventory:rename(function(item) return item:upper() end)
+				local ok, err = inventory:rename(function(item) error("no name") end)
+				assert(ok == nil and tostring(err):find("no name", 1, true))
+			"#,
+		)
+		.exec()?;
+
+		let inventory: Inventory = lua.globals().get("inventory")?;
+		assert_eq!(inventory.items, vec!["APPLE", "AXE", "BREAD"]);
+
+		return Ok(());
+	}
+
+	#[test]
+	fn iterators() -> LuaResult<()> {
+		let lua: Lua = Lua::new();
+		mlua_magic_macros::load!(lua, Bag);
+		lua.globals().set(
+			"bag",
+			Bag {
+				items: vec!["rope".to_string(), "torch".to_string()],
+			},
+		)?;
+
+		lua.load(
+			r#"
+				local seen = {}
+				for item in bag:each() do
+					table.insert(seen, item)
+				end
+				assert(#seen == 2 and seen[2] == "torch")
+
+				for index, item in bag:indexed() do
+					assert(seen[index] == item)
+				end
+
+				local total = 0
+				for length in bag:lengths() do
+					total = total + length
+				end
+				assert(total == 9)
+
+				local steps = {}
+				for step in Bag.countdown(3) do
+					table.insert(steps, step)
+				end
+				assert(table.concat(steps, ",") == "3,2,1")
+
+				-- Leaving the loop early releases the borrow
+				for item in bag:each() do
+					break
+				end
+				bag:add("map")
+
+				-- ... but the bag cannot change while it is being iterated
+				for item in bag:each() do
+					local ok = pcall(bag.add, bag, "coin")
+					assert(not ok)
+				end
+			"#,
+		)
+		.exec()?;
+
+		let bag: Bag = lua.globals().get("bag")?;
+		assert_eq!(bag.items, vec!["rope", "torch", "map"]);
+
+		return Ok(());
+	}
+
+	#[test]
+	fn overloads() -> LuaResult<()> {
+		let lua: Lua = Lua::new();
+		mlua_magic_macros::load!(lua, Span);
+
+		lua.load(
+			r#"
+				assert(Span.new().high == 0)
+				assert(Span.new(4).high == 4)
+				assert(Span.new("1..3").low == 1)
+				assert(Span.new(2, 5).low == 2)
+
+				local span = Span.new(0, 10)
+				assert(span:contains(5) and not span:contains(11))
+				assert(span:contains(Span.new(2, 3)) and not span:contains(Span.new(9, 12)))
+
+				-- Defaulted arguments may be left out of an overload
+				assert(span:widened().low == -1 and span:widened(2).high == 12)
+				assert(span:widened(Span.new(-5, 5)).low == -5)
+
+				span:shift(1)
+				span:shift(1, -1)
+
+				local ok, err = pcall(Span.new, true)
+				assert(not ok and tostring(err):find("no overload of 'new' takes (boolean)", 1, true))
+				assert(tostring(err):find("new(f32, f32)", 1, true))
+
+				last = span
+			"#,
+		)
+		.exec()?;
+
+		assert_eq!(
+			lua.globals().get::<Span>("last")?,
+			Span {
+				low: 2.0,
+				high: 10.0
+			}
+		);
+
+		return Ok(());
+	}
+
+	#[test]
+	fn export_policy() -> LuaResult<()> {
+		let lua: Lua = Lua::new();
+		mlua_magic_macros::load!(lua, Vault);
+
+		lua.load(
+			r#"
+				assert(Vault.CAPACITY == 1000 and Vault.FEE == nil)
+
+				local vault = Vault.new()
+				vault:deposit(11)
+				assert(vault:peek() == 10)
+				assert(vault.audit == nil and vault.empty == nil)
+			"#,
+		)
+		.exec()?;
+
+		return Ok(());
+	}
+}
@@ -0,0 +1,171 @@
+#[cfg(test)]
+pub mod meta {
+	use ::mlua::prelude::*;
+
+	use ::mlua_magic_macros;
+
+	use ::std::fmt;
+
+	#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+	#[mlua_magic_macros::enumeration]
+	pub enum Rank {
+		Bronze,
+		Silver,
+		Gold,
+	}
+
+	impl fmt::Display for Rank {
+		fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+			return write!(f, "rank {:?}", self);
+		}
+	}
+
+	mlua_magic_macros::compile!(type_path = Rank, variants = true, meta(display, eq, ord));
+
+	#[derive(Debug, Clone, Default, PartialEq)]
+	#[mlua_magic_macros::structure]
+	pub struct Point {
+		x: i32,
+		y: i32,
+	}
+
+	mlua_magic_macros::compile!(type_path = Point, fields = true, meta(debug, eq));
+
+	#[derive(Debug, Clone, Default)]
+	#[mlua_magic_macros::structure]
+	pub struct Body {
+		#[lua(project)]
+		center: Point,
+	}
+
+	mlua_magic_macros::compile!(type_path = Body, fields = true);
+
+	#[derive(Debug, Clone, Copy, Default, PartialEq)]
+	#[mlua_magic_macros::structure]
+	pub struct Vec2 {
+		x: f32,
+		y: f32,
+	}
+
+	impl ::std::ops::Add for Vec2 {
+		type Output = Vec2;
+
+		fn add(self, rhs: Vec2) -> Vec2 {
+			return Vec2 {
+				x: self.x + rhs.x,
+				y: self.y + rhs.y,
+			};
+		}
+	}
+
+	impl ::std::ops::Sub for Vec2 {
+		type Output = Vec2;
+
+		fn sub(self, rhs: Vec2) -> Vec2 {
+			return Vec2 {
+				x: self.x - rhs.x,
+				y: self.y - rhs.y,
+			};
+		}
+	}
+
+	impl ::std::ops::Mul<f32> for Vec2 {
+		type Output = Vec2;
+
+		fn mul(self, rhs: f32) -> Vec2 {
+			return Vec2 {
+				x: self.x * rhs,
+				y: self.y * rhs,
+			};
+		}
+	}
+
+	impl ::std::ops::Neg for Vec2 {
+		type Output = Vec2;
+
+		fn neg(self) -> Vec2 {
+			return Vec2 {
+				x: -self.x,
+				y: -self.y,
+			};
+		}
+	}
+
+	impl Vec2 {
+		fn get(&self, index: usize) -> Option<f32> {
+			return match index {
+				1 => Some(self.x),
+				2 => Some(self.y),
+				_ => None,
+			};
+		}
+	}
+
+	mlua_magic_macros::compile!(
+		type_path = Vec2,
+		fields = true,
+		meta(eq),
+		ops(add, sub, mul = f32, neg, index = Vec2::get)
+	);
+
+	#[test]
+	fn std_trait_metamethods() -> LuaResult<()> {
+		let lua: Lua = Lua::new();
+		mlua_magic_macros::load!(lua, Rank);
+		lua.globals().set("a", Point { x: 1, y: 2 })?;
+		lua.globals().set("b", Point { x: 1, y: 2 })?;
+		lua.globals().set("body", Body::default())?;
+
+		lua.load(
+			r#"
+				assert(tostring(Rank.Gold) == "rank Gold")
+				assert(Rank.Gold == Rank.Gold)
+				assert(Rank.Gold ~= Rank.Silver)
+				assert(Rank.Bronze < Rank.Silver)
+				assert(Rank.Gold <= Rank.Gold)
+				assert(not (Rank.Gold < Rank.Bronze))
+
+				assert(tostring(a) == "Point { x: 1, y: 2 }")
+				assert(a == b)
+				assert(a ~= Rank.Gold)
+
+				-- Projections behave like the value they point at
+				body.center.x = 1
+				body.center.y = 2
+				assert(tostring(body.center) == "Point { x: 1, y: 2 }")
+				assert(body.center == a)
+			"#,
+		)
+		.exec()?;
+
+		return Ok(());
+	}
+
+	#[test]
+	fn operator_metamethods() -> LuaResult<()> {
+		let lua: Lua = Lua::new();
+		lua.globals().set("a", Vec2 { x: 1.0, y: 2.0 })?;
+		lua.globals().set("b", Vec2 { x: 3.0, y: 4.0 })?;
+
+		lua.load(
+			r#"
+				local sum = a + b
+				assert(sum.x == 4 and sum.y == 6)
+				assert((b - a).x == 2 and (a * 2) == (a + a))
+				assert((a * 2) == (2 * a))
+				assert((-a).x == -1)
+				assert(a[1] == 1 and a[2] == 2)
+				assert(a.x == 1)
+
+				assert(not pcall(function() return a / 2 end))
+				assert(not pcall(function() return a + 1 end))
+				assert(not pcall(function() return 2 - a end))
+				assert(not pcall(function() return a.z end))
+				assert(a[5] == nil and a[0] == nil)
+			"#,
+		)
+		.exec()?;
+
+		return Ok(());
+	}
+}
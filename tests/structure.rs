@@ -0,0 +1,239 @@
+#[cfg(test)]
+pub mod structure {
+	use ::mlua::prelude::*;
+
+	use ::mlua_magic_macros;
+
+	#[derive(Debug, Clone, Default)]
+	#[mlua_magic_macros::structure]
+	pub struct Account {
+		#[lua(readonly)]
+		id: u64,
+		#[lua(rename = "displayName")]
+		display_name: String,
+		#[lua(writeonly)]
+		password: String,
+		#[lua(skip)]
+		dirty: bool,
+	}
+
+	mlua_magic_macros::compile!(type_path = Account, fields = true);
+
+	#[derive(Debug, Clone, Default, PartialEq)]
+	#[mlua_magic_macros::structure]
+	pub struct Position {
+		x: f32,
+		y: f32,
+	}
+
+	mlua_magic_macros::compile!(type_path = Position, fields = true);
+
+	#[derive(Debug, Clone, Default)]
+	#[mlua_magic_macros::structure]
+	pub struct Inventory {
+		items: Vec<String>,
+	}
+
+	#[mlua_magic_macros::implementation]
+	impl Inventory {
+		pub fn add(&mut self, item: String) -> () {
+			self.items.push(item);
+		}
+
+		pub fn count(&self) -> usize {
+			return self.items.len();
+		}
+
+		pub fn into_items(self) -> Vec<String> {
+			return self.items;
+		}
+	}
+
+	mlua_magic_macros::compile!(type_path = Inventory, fields = true, methods = true);
+
+	#[derive(Debug, Clone, Default)]
+	#[mlua_magic_macros::structure]
+	pub struct Hero {
+		name: String,
+		#[lua(project)]
+		pos: Position,
+		#[lua(project)]
+		inventory: Inventory,
+	}
+
+	mlua_magic_macros::compile!(type_path = Hero, fields = true);
+
+	#[derive(Debug, Clone, Default)]
+	#[mlua_magic_macros::structure]
+	pub struct World {
+		#[lua(project)]
+		hero: Hero,
+		spawn: Position,
+	}
+
+	mlua_magic_macros::compile!(type_path = World, fields = true);
+
+	#[derive(Debug, Clone, Copy, Default, PartialEq)]
+	#[mlua_magic_macros::structure]
+	pub struct Color(u8, u8, #[lua(readonly)] u8);
+
+	mlua_magic_macros::compile!(type_path = Color, fields = true);
+
+	#[derive(Debug, Clone, Copy, Default, PartialEq)]
+	#[mlua_magic_macros::structure(positional = "named")]
+	pub struct Meters(f64);
+
+	mlua_magic_macros::compile!(type_path = Meters, fields = true);
+
+	#[derive(Debug, Clone, Copy, Default, PartialEq)]
+	#[mlua_magic_macros::structure]
+	pub struct Marker;
+
+	mlua_magic_macros::compile!(type_path = Marker, fields = true);
+
+	#[derive(Debug, Clone, Default)]
+	#[mlua_magic_macros::structure(export = "pub")]
+	pub struct Wallet {
+		pub owner: String,
+		pub(crate) coins: u32,
+		secret: String,
+		#[lua(export)]
+		nonce: u32,
+		#[lua(skip)]
+		pub audited: bool,
+	}
+
+	mlua_magic_macros::compile!(type_path = Wallet, fields = true);
+
+	#[test]
+	fn field_attributes() -> LuaResult<()> {
+		let lua: Lua = Lua::new();
+		let account: Account = Account {
+			id: 7,
+			display_name: "Hero".to_string(),
+			password: "hunter2".to_string(),
+			dirty: false,
+		};
+		lua.globals().set("account", account)?;
+
+		lua.load(
+			r#"
+				assert(account.id == 7)
+				assert(account.displayName == "Hero")
+				assert(not pcall(function() return account.display_name end))
+				account.displayName = "Villain"
+				account.password = "swordfish"
+
+				assert(not pcall(function() account.id = 8 end))
+				assert(not pcall(function() return account.password end))
+				assert(not pcall(function() return account.dirty end))
+			"#,
+		)
+		.exec()?;
+
+		let account: Account = lua.globals().get("account")?;
+		assert_eq!(account.id, 7);
+		assert_eq!(account.display_name, "Villain");
+		assert_eq!(account.password, "swordfish");
+		assert!(!account.dirty);
+
+		return Ok(());
+	}
+
+	#[test]
+	fn nested_projection() -> LuaResult<()> {
+		let lua: Lua = Lua::new();
+		lua.globals().set("world", World::default())?;
+
+		lua.load(
+			r#"
+				local hero = world.hero
+				hero.name = "Hero"
+				hero.pos.x = 5
+				world.hero.pos.y = 7
+				world.hero.inventory:add("sword")
+				hero.inventory:add("shield")
+				assert(world.hero.inventory:count() == 2)
+				assert(world.hero.pos.x == 5)
+
+				-- A projection cannot give up the value it points at
+				local ok, err = pcall(function() return world.hero.inventory:into_items() end)
+				assert(not ok and tostring(err):find("projected field"))
+				assert(world.hero.inventory:count() == 2)
+
+				-- Plain fields still hand out copies
+				world.spawn.x = 3
+				assert(world.spawn.x == 0)
+
+				-- Projections convert back into owned values
+				world.spawn = world.hero.pos
+			"#,
+		)
+		.exec()?;
+
+		let world: World = lua.globals().get("world")?;
+		assert_eq!(world.hero.name, "Hero");
+		assert_eq!(world.hero.pos, Position { x: 5.0, y: 7.0 });
+		assert_eq!(world.hero.inventory.items, vec!["sword", "shield"]);
+		assert_eq!(world.spawn, Position { x: 5.0, y: 7.0 });
+
+		return Ok(());
+	}
+
+	#[test]
+	fn tuple_and_unit_structs() -> LuaResult<()> {
+		let lua: Lua = Lua::new();
+		lua.globals().set("color", Color(1, 2, 3))?;
+		lua.globals().set("meters", Meters(1.5))?;
+		lua.globals().set("marker", Marker)?;
+
+		lua.load(
+			r#"
+				assert(color[1] == 1 and color[2] == 2 and color[3] == 3)
+				color[1] = 10
+				assert(not pcall(function() color[3] = 30 end))
+				assert(not pcall(function() return color[4] end))
+
+				assert(meters._0 == 1.5)
+				meters._0 = 2.5
+
+				assert(type(marker) == "userdata")
+			"#,
+		)
+		.exec()?;
+
+		assert_eq!(lua.globals().get::<Color>("color")?, Color(10, 2, 3));
+		assert_eq!(lua.globals().get::<Meters>("meters")?, Meters(2.5));
+		assert_eq!(lua.globals().get::<Marker>("marker")?, Marker);
+
+		return Ok(());
+	}
+
+	#[test]
+	fn export_policy() -> LuaResult<()> {
+		let lua: Lua = Lua::new();
+		let wallet: Wallet = Wallet {
+			owner: "Hero".to_string(),
+			coins: 3,
+			secret: "1234".to_string(),
+			nonce: 9,
+			audited: true,
+		};
+		lua.globals().set("wallet", wallet)?;
+
+		lua.load(
+			r#"
+				assert(wallet.owner == "Hero" and wallet.coins == 3 and wallet.nonce == 9)
+				assert(not pcall(function() return wallet.secret end))
+				assert(not pcall(function() return wallet.audited end))
+			"#,
+		)
+		.exec()?;
+
+		let wallet: Wallet = lua.globals().get("wallet")?;
+		assert_eq!(wallet.secret, "1234");
+		assert!(wallet.audited);
+
+		return Ok(());
+	}
+}